@@ -0,0 +1,547 @@
+//! 穴とぼかしの強さの切り替えアニメーション
+//!
+//! フォーカスが移ったときに穴が瞬間移動したり、ぼかしが急に変わったりしないよう、
+//! 目標値に向かって時間で補間する。時刻は `Clock` から取るので、`ManualClock` を渡せば
+//! 実時間を待たずに任意の時点の値を確かめられる。
+
+use crate::geometry::PhysicalRect;
+use crate::window_manager::WindowInfo;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// 切り替えにかける時間の上限（ミリ秒）
+pub const MAX_TRANSITION_MS: u32 = 2000;
+
+/// 現在時刻の取得元
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// 実時間の時計
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// テスト用の手で進める時計
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: std::cell::Cell<Instant>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            now: std::cell::Cell::new(Instant::now()),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+#[cfg(test)]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+/// 補間の緩急
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// 始めは速く、終わりにかけてゆっくり（3 次）
+    #[default]
+    EaseOut,
+    /// 始めと終わりがゆっくり（3 次）
+    EaseInOut,
+}
+
+impl Easing {
+    /// 経過の割合 `t`（0..=1）を補間の割合に変換する
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// 切り替えアニメーションの設定
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transition {
+    /// 切り替えにかける時間（ミリ秒）。0 なら即座に切り替える
+    pub duration_ms: u32,
+    pub easing: Easing,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self {
+            duration_ms: 150,
+            easing: Easing::default(),
+        }
+    }
+}
+
+impl Transition {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(u64::from(self.duration_ms))
+    }
+}
+
+/// 補間できる値
+pub trait Lerp: Copy {
+    fn lerp(from: Self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        from + (to - from) * t
+    }
+}
+
+/// 穴 1 つ分の補間する値（物理スクリーン座標の矩形と角丸半径）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoleShape {
+    pub rect: [f32; 4],
+    pub corner_radius: f32,
+}
+
+impl HoleShape {
+    pub fn new(rect: PhysicalRect, corner_radius: i32) -> Self {
+        Self {
+            rect: [
+                rect.left as f32,
+                rect.top as f32,
+                rect.right as f32,
+                rect.bottom as f32,
+            ],
+            corner_radius: corner_radius as f32,
+        }
+    }
+
+    /// 中心に潰した形（穴が現れる・消えるときの始点・終点）
+    pub fn collapsed(&self) -> Self {
+        let cx = (self.rect[0] + self.rect[2]) / 2.0;
+        let cy = (self.rect[1] + self.rect[3]) / 2.0;
+        Self {
+            rect: [cx, cy, cx, cy],
+            corner_radius: 0.0,
+        }
+    }
+
+    pub fn rect(&self) -> PhysicalRect {
+        PhysicalRect {
+            left: self.rect[0].round() as i32,
+            top: self.rect[1].round() as i32,
+            right: self.rect[2].round() as i32,
+            bottom: self.rect[3].round() as i32,
+        }
+    }
+}
+
+impl Lerp for HoleShape {
+    fn lerp(from: Self, to: Self, t: f32) -> Self {
+        Self {
+            rect: std::array::from_fn(|i| f32::lerp(from.rect[i], to.rect[i], t)),
+            corner_radius: f32::lerp(from.corner_radius, to.corner_radius, t),
+        }
+    }
+}
+
+/// `from` から `to` へ `start` から `duration` かけて変わる値
+#[derive(Debug, Clone, Copy)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub start: Instant,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    /// 最初から `value` で止まっている状態
+    pub fn settled(value: T, now: Instant) -> Self {
+        Self {
+            from: value,
+            to: value,
+            start: now,
+            duration: Duration::ZERO,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// 経過の割合（0..=1）
+    pub fn progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.start);
+        (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+    }
+
+    pub fn value(&self, now: Instant) -> T {
+        T::lerp(self.from, self.to, self.easing.apply(self.progress(now)))
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// 今の値から `to` に向かって補間し直す（途中で目標が変わっても飛ばない）
+    pub fn retarget(&mut self, to: T, now: Instant, transition: &Transition) {
+        self.from = self.value(now);
+        self.to = to;
+        self.start = now;
+        self.duration = transition.duration();
+        self.easing = transition.easing;
+    }
+}
+
+/// 穴 1 つ分のアニメーション
+#[derive(Debug, Clone)]
+struct HoleSlot {
+    shape: Tween<HoleShape>,
+    /// 最後に目標にしたウィンドウ
+    window: WindowInfo,
+    /// 穴が減って、中心へ縮んで消えていく途中
+    leaving: bool,
+}
+
+/// アニメーションの状態
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationState {
+    /// すべて目標値で止まっている
+    Idle,
+    /// 補間の途中
+    Running,
+}
+
+/// 穴の位置・角丸とぼかしの強さの切り替えを補間する
+///
+/// 穴は並び順（先頭がフォアグラウンド）で対応させる。フォーカスが移ると先頭の穴が
+/// 前のウィンドウから新しいウィンドウへ滑らかに移り、増えた穴は中心から広がり、
+/// 減った穴は中心へ縮んで消える。
+pub struct HoleAnimator<C: Clock = SystemClock> {
+    pub clock: C,
+    pub transition: Transition,
+    slots: Vec<HoleSlot>,
+    strength: Tween<f32>,
+}
+
+impl<C: Clock> HoleAnimator<C> {
+    pub fn new(clock: C, transition: Transition, blur_strength: f32) -> Self {
+        let now = clock.now();
+        Self {
+            clock,
+            transition,
+            slots: Vec::new(),
+            strength: Tween::settled(blur_strength, now),
+        }
+    }
+
+    /// 穴の目標を設定する。`windows` は `Params::set_holes` に渡す順と同じ並び
+    pub fn set_holes(&mut self, windows: &[WindowInfo]) {
+        let now = self.clock.now();
+        self.prune(now);
+        for (i, window) in windows.iter().enumerate() {
+            let target = HoleShape::new(window.rect, window.corner_radius);
+            match self.slots.get_mut(i) {
+                Some(slot) => {
+                    if slot.shape.to != target {
+                        slot.shape.retarget(target, now, &self.transition);
+                    }
+                    slot.window = window.clone();
+                    slot.leaving = false;
+                }
+                None => {
+                    let mut shape = Tween::settled(target.collapsed(), now);
+                    shape.retarget(target, now, &self.transition);
+                    self.slots.push(HoleSlot {
+                        shape,
+                        window: window.clone(),
+                        leaving: false,
+                    });
+                }
+            }
+        }
+        for slot in self.slots.iter_mut().skip(windows.len()) {
+            if !slot.leaving {
+                slot.leaving = true;
+                let target = slot.shape.to.collapsed();
+                slot.shape.retarget(target, now, &self.transition);
+            }
+        }
+    }
+
+    /// ぼかしの強さの目標を設定する
+    pub fn set_strength(&mut self, blur_strength: f32) {
+        if self.strength.to != blur_strength {
+            let now = self.clock.now();
+            self.strength.retarget(blur_strength, now, &self.transition);
+        }
+    }
+
+    /// 今の時点の穴。矩形と角丸を補間した値に置き換えたウィンドウ情報を返す
+    pub fn holes(&self) -> Vec<WindowInfo> {
+        let now = self.clock.now();
+        self.slots
+            .iter()
+            .filter_map(|slot| {
+                let shape = slot.shape.value(now);
+                let mut window = slot.window.clone();
+                window.rect = shape.rect();
+                window.corner_radius = shape.corner_radius.round() as i32;
+                (!window.rect.is_empty()).then_some(window)
+            })
+            .collect()
+    }
+
+    pub fn strength(&self) -> f32 {
+        self.strength.value(self.clock.now())
+    }
+
+    #[cfg(test)]
+    pub fn state(&self) -> AnimationState {
+        let now = self.clock.now();
+        let running =
+            !self.strength.is_finished(now) || self.slots.iter().any(|s| !s.shape.is_finished(now));
+        if running {
+            AnimationState::Running
+        } else {
+            AnimationState::Idle
+        }
+    }
+
+    /// 縮み切った穴を末尾から取り除く
+    fn prune(&mut self, now: Instant) {
+        while self
+            .slots
+            .last()
+            .is_some_and(|s| s.leaving && s.shape.is_finished(now))
+        {
+            self.slots.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn transition(duration_ms: u32, easing: Easing) -> Transition {
+        Transition {
+            duration_ms,
+            easing,
+        }
+    }
+
+    fn window(hwnd: isize, rect: PhysicalRect, corner_radius: i32) -> WindowInfo {
+        WindowInfo {
+            hwnd,
+            title: String::new(),
+            class_name: String::new(),
+            process_name: String::new(),
+            pid: 0,
+            owner: None,
+            styles: Vec::new(),
+            visible: true,
+            rect,
+            corner_radius,
+        }
+    }
+
+    fn rects(animator: &HoleAnimator<ManualClock>) -> Vec<PhysicalRect> {
+        animator.holes().iter().map(|w| w.rect).collect()
+    }
+
+    #[test]
+    fn easing_curves() {
+        for easing in [Easing::Linear, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.875);
+        assert_eq!(Easing::EaseInOut.apply(0.25), 0.0625);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert_eq!(Easing::EaseInOut.apply(0.75), 0.9375);
+    }
+
+    #[test]
+    fn tween_follows_easing_until_finished() {
+        let clock = ManualClock::new();
+        let mut tween = Tween::settled(0.0, clock.now());
+        tween.retarget(100.0, clock.now(), &transition(200, Easing::EaseOut));
+        assert_eq!(tween.value(clock.now()), 0.0);
+
+        clock.advance(100 * MS);
+        assert_eq!(tween.progress(clock.now()), 0.5);
+        assert_eq!(tween.value(clock.now()), 87.5);
+        assert!(!tween.is_finished(clock.now()));
+
+        clock.advance(100 * MS);
+        assert_eq!(tween.value(clock.now()), 100.0);
+        assert!(tween.is_finished(clock.now()));
+        clock.advance(1000 * MS);
+        assert_eq!(tween.value(clock.now()), 100.0);
+    }
+
+    #[test]
+    fn zero_duration_switches_immediately() {
+        let clock = ManualClock::new();
+        let mut tween = Tween::settled(10.0, clock.now());
+        tween.retarget(40.0, clock.now(), &transition(0, Easing::EaseInOut));
+        assert_eq!(tween.value(clock.now()), 40.0);
+        assert!(tween.is_finished(clock.now()));
+    }
+
+    #[test]
+    fn retarget_mid_tween_starts_from_current_value() {
+        let clock = ManualClock::new();
+        let linear = transition(100, Easing::Linear);
+        let mut tween = Tween::settled(0.0, clock.now());
+        tween.retarget(100.0, clock.now(), &linear);
+        clock.advance(25 * MS);
+        assert_eq!(tween.value(clock.now()), 25.0);
+
+        // 途中で目標が変わっても値は飛ばず、そこから新しい時間をかけて向かう
+        tween.retarget(-75.0, clock.now(), &linear);
+        assert_eq!(tween.value(clock.now()), 25.0);
+        clock.advance(50 * MS);
+        assert_eq!(tween.value(clock.now()), -25.0);
+        clock.advance(50 * MS);
+        assert_eq!(tween.value(clock.now()), -75.0);
+        assert!(tween.is_finished(clock.now()));
+    }
+
+    #[test]
+    fn new_hole_grows_from_its_center() {
+        let mut animator =
+            HoleAnimator::new(ManualClock::new(), transition(100, Easing::Linear), 20.0);
+        let target = PhysicalRect::from_origin_size(100, 100, 200, 100);
+        animator.set_holes(&[window(1, target, 8)]);
+        // 始めは中心に潰れているので空の穴は返さない
+        assert!(animator.holes().is_empty());
+        assert_eq!(animator.state(), AnimationState::Running);
+
+        animator.clock.advance(50 * MS);
+        assert_eq!(
+            rects(&animator),
+            [PhysicalRect::from_origin_size(150, 125, 100, 50)]
+        );
+        assert_eq!(animator.holes()[0].corner_radius, 4);
+
+        animator.clock.advance(50 * MS);
+        assert_eq!(rects(&animator), [target]);
+        assert_eq!(animator.holes()[0].corner_radius, 8);
+        assert_eq!(animator.state(), AnimationState::Idle);
+    }
+
+    #[test]
+    fn focus_change_slides_the_first_hole() {
+        let mut animator =
+            HoleAnimator::new(ManualClock::new(), transition(100, Easing::Linear), 20.0);
+        let first = PhysicalRect::from_origin_size(0, 0, 100, 100);
+        let second = PhysicalRect::from_origin_size(200, 100, 300, 100);
+        animator.set_holes(&[window(1, first, 0)]);
+        animator.clock.advance(100 * MS);
+
+        animator.set_holes(&[window(2, second, 0)]);
+        animator.clock.advance(50 * MS);
+        let holes = animator.holes();
+        assert_eq!(holes[0].hwnd, 2);
+        assert_eq!(
+            holes[0].rect,
+            PhysicalRect::from_origin_size(100, 50, 200, 100)
+        );
+
+        // 半分まで来たところで元のウィンドウに戻ると、今の位置から戻り始める
+        animator.set_holes(&[window(1, first, 0)]);
+        assert_eq!(
+            rects(&animator),
+            [PhysicalRect::from_origin_size(100, 50, 200, 100)]
+        );
+        animator.clock.advance(100 * MS);
+        assert_eq!(rects(&animator), [first]);
+    }
+
+    #[test]
+    fn removed_hole_shrinks_and_is_dropped() {
+        let mut animator =
+            HoleAnimator::new(ManualClock::new(), transition(100, Easing::Linear), 20.0);
+        let a = PhysicalRect::from_origin_size(0, 0, 100, 100);
+        let b = PhysicalRect::from_origin_size(400, 0, 100, 100);
+        animator.set_holes(&[window(1, a, 0), window(2, b, 0)]);
+        animator.clock.advance(100 * MS);
+        assert_eq!(rects(&animator), [a, b]);
+
+        animator.set_holes(&[window(1, a, 0)]);
+        animator.clock.advance(50 * MS);
+        assert_eq!(
+            rects(&animator),
+            [a, PhysicalRect::from_origin_size(425, 25, 50, 50)]
+        );
+        animator.clock.advance(50 * MS);
+        assert_eq!(rects(&animator), [a]);
+        assert_eq!(animator.state(), AnimationState::Idle);
+
+        // 縮み切った穴は次の更新で取り除かれ、新しい穴は中心から広がる
+        animator.set_holes(&[window(1, a, 0), window(3, b, 0)]);
+        animator.clock.advance(50 * MS);
+        assert_eq!(
+            rects(&animator),
+            [a, PhysicalRect::from_origin_size(425, 25, 50, 50)]
+        );
+    }
+
+    #[test]
+    fn strength_is_interpolated() {
+        let mut animator =
+            HoleAnimator::new(ManualClock::new(), transition(200, Easing::EaseOut), 10.0);
+        assert_eq!(animator.strength(), 10.0);
+        assert_eq!(animator.state(), AnimationState::Idle);
+
+        animator.set_strength(50.0);
+        animator.clock.advance(100 * MS);
+        assert_eq!(animator.strength(), 45.0);
+        assert_eq!(animator.state(), AnimationState::Running);
+
+        // 同じ目標を設定し直しても始めからやり直さない
+        animator.set_strength(50.0);
+        animator.clock.advance(100 * MS);
+        assert_eq!(animator.strength(), 50.0);
+        assert_eq!(animator.state(), AnimationState::Idle);
+    }
+}
@@ -0,0 +1,615 @@
+//! オーバーレイにぼかしを付ける方式
+//!
+//! OS の合成に任せる Mica / Mica Alt / Acrylic / 旧来のブラー、wgpu の `Renderer`、CPU の `CpuRenderer`、何も描かない
+//! `NullBackend` を `BlurBackend` として同じように扱う。描画スレッドは設定で選んだ方式から
+//! `OverlayBackend::fallback_chain` の順に初期化を試し、最初に使えたものを使う。
+//! `RecordingBackend` は呼ばれた内容を記録するだけなので、描画の流れをテストで確かめられる。
+
+#[cfg(windows)]
+pub mod dwm;
+
+use crate::capture::Frame;
+use crate::renderer::cpu::CpuRenderer;
+use crate::renderer::{FrameRenderer, Params, Renderer};
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+/// オーバーレイのぼかしをどう描くか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayBackend {
+    /// window-vibrancy による DWM の Acrylic（穴は z オーダーで作る）
+    #[default]
+    SystemAcrylic,
+    /// DWM の Mica（Windows 11 以降。壁紙の色をうっすら映す）
+    SystemMica,
+    /// DWM の Mica Alt（タブ付きウィンドウ向けの Mica。Windows 11 22H2 以降）
+    SystemTabbed,
+    /// Windows 7 以来の DWM のブラー（Acrylic が使えない古い Windows 10 向け）
+    SystemBlur,
+    /// 画面キャプチャを wgpu でぼかし、穴をシェーダーで抜く
+    GpuBlur,
+    /// 画面キャプチャを CPU でぼかす（GPU が使えない環境向け）
+    CpuBlur,
+    /// 何も描かない（どの方式も使えなかったときの最後の手段）
+    Null,
+}
+
+impl OverlayBackend {
+    /// OS の合成でぼかす方式
+    pub const SYSTEM_BACKDROPS: [OverlayBackend; 4] = [
+        OverlayBackend::SystemTabbed,
+        OverlayBackend::SystemMica,
+        OverlayBackend::SystemAcrylic,
+        OverlayBackend::SystemBlur,
+    ];
+
+    /// 使えなかったときに順に試す方式（先頭が `self`）
+    ///
+    /// OS の合成 → GPU → CPU → 何もしない、の順に落ちていく。
+    pub fn fallback_chain(self) -> &'static [OverlayBackend] {
+        const CHAIN: [OverlayBackend; 7] = [
+            OverlayBackend::SystemTabbed,
+            OverlayBackend::SystemMica,
+            OverlayBackend::SystemAcrylic,
+            OverlayBackend::SystemBlur,
+            OverlayBackend::GpuBlur,
+            OverlayBackend::CpuBlur,
+            OverlayBackend::Null,
+        ];
+        let start = CHAIN.iter().position(|&b| b == self).unwrap_or(0);
+        &CHAIN[start..]
+    }
+
+    /// OS の合成でぼかす方式か（フェードはシェーダーではなくウィンドウのアルファで行う）
+    pub fn is_system_backdrop(self) -> bool {
+        Self::SYSTEM_BACKDROPS.contains(&self)
+    }
+
+    /// この Windows のバージョンで使えるか（window-vibrancy と同じ判定）
+    ///
+    /// OS の合成でぼかす方式だけをビルド番号で見分ける。それ以外は初期化してみないと分からないので `true`。
+    #[cfg(any(windows, test))]
+    pub fn supported_on_windows(self, major: u32, minor: u32, build: u32) -> bool {
+        // Acrylic と 10 以降のブラーは SetWindowCompositionAttribute（1809 以降）、Mica は
+        // DWMWA_MICA_EFFECT（11 以降）、Mica Alt は DWMWA_SYSTEMBACKDROP_TYPE（22H2 以降）で付ける
+        match self {
+            OverlayBackend::SystemBlur => (major, minor) == (6, 1) || build >= 17763,
+            OverlayBackend::SystemAcrylic => build >= 17763,
+            OverlayBackend::SystemMica => build >= 22000,
+            OverlayBackend::SystemTabbed => build >= 22523,
+            _ => true,
+        }
+    }
+}
+
+/// Mica / Mica Alt を明るい色・暗い色のどちらで描かせるか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackdropTheme {
+    /// OS のアプリモード（ライト / ダーク）に合わせる
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl BackdropTheme {
+    /// window-vibrancy の `dark` 引数（`None` なら OS に合わせる）
+    #[cfg(windows)]
+    pub fn dark(self) -> Option<bool> {
+        match self {
+            BackdropTheme::System => None,
+            BackdropTheme::Light => Some(false),
+            BackdropTheme::Dark => Some(true),
+        }
+    }
+}
+
+/// 設定 UI に返す描画方式の状況
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BackdropStatus {
+    /// 設定で選んだ方式
+    pub requested: OverlayBackend,
+    /// 実際に使っている方式（使えなければ `requested` より後ろに落ちている）
+    pub applied: OverlayBackend,
+    /// この環境で使えない方式（UI で選べなくする）
+    ///
+    /// OS のバージョンから使えないと分かっている方式と、初期化に失敗したことのある方式。
+    pub unavailable: Vec<OverlayBackend>,
+}
+
+/// 新しいキャプチャと、その中で描画先の左上に当たる位置（物理ピクセル）
+pub type CapturedFrame<'a, 'b> = (&'a Frame<'b>, (u32, u32));
+
+/// オーバーレイ 1 枚分のぼかしの描き方
+///
+/// 描画スレッドが `init` → (`resize` / `set_params` / `render_frame`)* → `shutdown` の順に呼ぶ。
+pub trait BlurBackend {
+    fn kind(&self) -> OverlayBackend;
+
+    /// 描画を始める。この環境で使えなければエラーを返す（呼び出し側は次の方式に落とす）
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// 出力先の物理ピクセルサイズやスケールが変わった
+    fn resize(&mut self, width: u32, height: u32, scale_factor: f64);
+
+    /// 設定（ぼかしの方式・色合いなど）を反映する
+    fn set_params(&mut self, settings: &Settings);
+
+    /// 1 フレーム描く。`frame` は新しいキャプチャが届いたときだけ渡される
+    fn render_frame(
+        &mut self,
+        frame: Option<CapturedFrame>,
+        params: &Params,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// 画面キャプチャが要るか（OS の合成に任せる方式なら要らない）
+    fn needs_capture(&self) -> bool;
+
+    /// キャプチャが `Frame::Gpu` を書き込めるデバイス
+    fn device(&self) -> Option<&wgpu::Device> {
+        None
+    }
+
+    /// 後始末（ウィンドウに付けた効果を外すなど）。別の方式に切り替える前に呼ばれる
+    fn shutdown(&mut self);
+}
+
+/// `preferred` から `fallback_chain` の順に作って初期化し、最初に使えた方式と、
+/// その手前で使えなかった方式を返す
+///
+/// `create` が作れなかった方式や `init` に失敗した方式は飛ばす。すべて駄目なら `NullBackend`。
+pub fn select(
+    preferred: OverlayBackend,
+    mut create: impl FnMut(OverlayBackend) -> Result<Box<dyn BlurBackend>, Box<dyn std::error::Error>>,
+) -> (Box<dyn BlurBackend>, Vec<OverlayBackend>) {
+    let mut unavailable = Vec::new();
+    for &kind in preferred.fallback_chain() {
+        let result = create(kind).and_then(|mut backend| {
+            backend.init()?;
+            Ok(backend)
+        });
+        match result {
+            Ok(backend) => {
+                if kind != preferred {
+                    tracing::warn!("Falling back from {:?} to {:?}", preferred, kind);
+                }
+                return (backend, unavailable);
+            }
+            Err(e) => {
+                tracing::warn!("Backend {:?} unavailable: {}", kind, e);
+                unavailable.push(kind);
+            }
+        }
+    }
+    (Box::new(NullBackend), unavailable)
+}
+
+fn resize_renderer<R: FrameRenderer>(renderer: &mut R, width: u32, height: u32, scale: f64) {
+    if (width, height) != renderer.size() {
+        renderer.resize(width, height);
+    }
+    renderer.set_scale_factor(scale);
+}
+
+fn render_with<R: FrameRenderer>(
+    renderer: &mut R,
+    frame: Option<CapturedFrame>,
+    params: &Params,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some((frame, source_offset)) = frame {
+        renderer.upload_frame(frame, source_offset);
+    }
+    renderer.render(params)
+}
+
+/// アダプターは `Renderer::new` の時点で確保済み
+impl BlurBackend for Renderer {
+    fn kind(&self) -> OverlayBackend {
+        OverlayBackend::GpuBlur
+    }
+
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32, scale_factor: f64) {
+        resize_renderer(self, width, height, scale_factor);
+    }
+
+    fn set_params(&mut self, settings: &Settings) {
+        self.set_blur_mode(settings.blur_mode);
+    }
+
+    fn render_frame(
+        &mut self,
+        frame: Option<CapturedFrame>,
+        params: &Params,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        render_with(self, frame, params)
+    }
+
+    fn needs_capture(&self) -> bool {
+        true
+    }
+
+    fn device(&self) -> Option<&wgpu::Device> {
+        Some(&self.device)
+    }
+
+    fn shutdown(&mut self) {}
+}
+
+impl BlurBackend for CpuRenderer {
+    fn kind(&self) -> OverlayBackend {
+        OverlayBackend::CpuBlur
+    }
+
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32, scale_factor: f64) {
+        resize_renderer(self, width, height, scale_factor);
+    }
+
+    fn set_params(&mut self, settings: &Settings) {
+        self.set_blur_mode(settings.blur_mode);
+    }
+
+    fn render_frame(
+        &mut self,
+        frame: Option<CapturedFrame>,
+        params: &Params,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        render_with(self, frame, params)
+    }
+
+    fn needs_capture(&self) -> bool {
+        true
+    }
+
+    fn shutdown(&mut self) {}
+}
+
+/// 何も描かない
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullBackend;
+
+impl BlurBackend for NullBackend {
+    fn kind(&self) -> OverlayBackend {
+        OverlayBackend::Null
+    }
+
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32, _scale_factor: f64) {}
+
+    fn set_params(&mut self, _settings: &Settings) {}
+
+    fn render_frame(
+        &mut self,
+        _frame: Option<CapturedFrame>,
+        _params: &Params,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn needs_capture(&self) -> bool {
+        false
+    }
+
+    fn shutdown(&mut self) {}
+}
+
+/// `RecordingBackend` が記録した呼び出し
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub enum BackendCall {
+    Init,
+    Resize {
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    },
+    SetParams(Box<Settings>),
+    RenderFrame {
+        /// 新しいキャプチャが渡されたならそのサイズ
+        frame_size: Option<(u32, u32)>,
+        params: Box<Params>,
+    },
+    Shutdown,
+}
+
+/// 呼ばれた内容を記録するだけで何も描かない（テスト用）
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct RecordingBackend {
+    /// `kind` で名乗る方式（`select` にどの方式として返されたかを確かめるため）
+    pub kind: OverlayBackend,
+    /// 記録先。描画スレッドに渡した後もテストから読めるよう共有する
+    pub calls: Arc<Mutex<Vec<BackendCall>>>,
+    /// `init` を失敗させる（フォールバックを確かめるため）
+    pub fail_init: bool,
+}
+
+#[cfg(test)]
+impl RecordingBackend {
+    pub fn new(kind: OverlayBackend) -> Self {
+        Self {
+            kind,
+            calls: Arc::default(),
+            fail_init: false,
+        }
+    }
+
+    fn record(&self, call: BackendCall) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+#[cfg(test)]
+impl BlurBackend for RecordingBackend {
+    fn kind(&self) -> OverlayBackend {
+        self.kind
+    }
+
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.record(BackendCall::Init);
+        if self.fail_init {
+            return Err("recording backend was told to fail".into());
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32, scale_factor: f64) {
+        self.record(BackendCall::Resize {
+            width,
+            height,
+            scale_factor,
+        });
+    }
+
+    fn set_params(&mut self, settings: &Settings) {
+        self.record(BackendCall::SetParams(Box::new(settings.clone())));
+    }
+
+    fn render_frame(
+        &mut self,
+        frame: Option<CapturedFrame>,
+        params: &Params,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.record(BackendCall::RenderFrame {
+            frame_size: frame.map(|(frame, _)| frame.size()),
+            params: Box::new(*params),
+        });
+        Ok(())
+    }
+
+    fn needs_capture(&self) -> bool {
+        false
+    }
+
+    fn shutdown(&mut self) {
+        self.record(BackendCall::Shutdown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::synthetic::SyntheticFrameSource;
+    use crate::capture::FrameSource;
+    use crate::renderer::BlurMode;
+
+    type Created = Result<Box<dyn BlurBackend>, Box<dyn std::error::Error>>;
+
+    /// `fails` に含まれる方式は作成に、`init_fails` に含まれる方式は初期化に失敗させて選ぶ。
+    /// 作成を試した順も返す
+    fn select_with(
+        preferred: OverlayBackend,
+        fails: &[OverlayBackend],
+        init_fails: &[OverlayBackend],
+    ) -> (OverlayBackend, Vec<OverlayBackend>, Vec<OverlayBackend>) {
+        let mut tried = Vec::new();
+        let (backend, unavailable) = select(preferred, |kind| -> Created {
+            tried.push(kind);
+            if fails.contains(&kind) {
+                return Err(format!("{:?} is not supported", kind).into());
+            }
+            let mut backend = RecordingBackend::new(kind);
+            backend.fail_init = init_fails.contains(&kind);
+            Ok(Box::new(backend))
+        });
+        (backend.kind(), unavailable, tried)
+    }
+
+    #[test]
+    fn fallback_chain_starts_at_preferred() {
+        use OverlayBackend::*;
+        assert_eq!(
+            SystemMica.fallback_chain(),
+            [
+                SystemMica,
+                SystemAcrylic,
+                SystemBlur,
+                GpuBlur,
+                CpuBlur,
+                Null
+            ]
+        );
+        assert_eq!(GpuBlur.fallback_chain(), [GpuBlur, CpuBlur, Null]);
+        assert_eq!(Null.fallback_chain(), [Null]);
+        for backend in SystemTabbed.fallback_chain() {
+            assert_eq!(backend.fallback_chain()[0], *backend);
+            assert_eq!(backend.fallback_chain().last(), Some(&Null));
+        }
+    }
+
+    #[test]
+    fn system_backdrops() {
+        use OverlayBackend::*;
+        let system: Vec<_> = SystemTabbed
+            .fallback_chain()
+            .iter()
+            .filter(|b| b.is_system_backdrop())
+            .copied()
+            .collect();
+        assert_eq!(system, OverlayBackend::SYSTEM_BACKDROPS);
+        assert_eq!(
+            system,
+            [SystemTabbed, SystemMica, SystemAcrylic, SystemBlur]
+        );
+    }
+
+    #[test]
+    fn system_backdrops_depend_on_windows_build() {
+        use OverlayBackend::*;
+        const ALL: [OverlayBackend; 7] = [
+            SystemTabbed,
+            SystemMica,
+            SystemAcrylic,
+            SystemBlur,
+            GpuBlur,
+            CpuBlur,
+            Null,
+        ];
+        let supported = |major, minor, build| -> Vec<_> {
+            ALL.into_iter()
+                .filter(|kind| kind.supported_on_windows(major, minor, build))
+                .collect()
+        };
+
+        // Windows 7 は旧来のブラーだけ、8.1 と 1809 より前の 10 はどれも使えない
+        assert_eq!(supported(6, 1, 7601), [SystemBlur, GpuBlur, CpuBlur, Null]);
+        assert_eq!(supported(6, 3, 9600), [GpuBlur, CpuBlur, Null]);
+        assert_eq!(supported(10, 0, 17134), [GpuBlur, CpuBlur, Null]);
+        assert_eq!(
+            supported(10, 0, 19045),
+            [SystemAcrylic, SystemBlur, GpuBlur, CpuBlur, Null]
+        );
+        assert_eq!(
+            supported(10, 0, 22000),
+            [
+                SystemMica,
+                SystemAcrylic,
+                SystemBlur,
+                GpuBlur,
+                CpuBlur,
+                Null
+            ]
+        );
+        assert_eq!(supported(10, 0, 22631), ALL);
+    }
+
+    #[test]
+    fn select_uses_preferred_when_available() {
+        let (kind, unavailable, tried) = select_with(OverlayBackend::GpuBlur, &[], &[]);
+        assert_eq!(kind, OverlayBackend::GpuBlur);
+        assert!(unavailable.is_empty());
+        assert_eq!(tried, [OverlayBackend::GpuBlur]);
+    }
+
+    #[test]
+    fn select_walks_fallback_chain_and_reports_failures() {
+        use OverlayBackend::*;
+        let (kind, unavailable, tried) =
+            select_with(SystemTabbed, &[SystemTabbed, SystemMica], &[SystemAcrylic]);
+        assert_eq!(kind, SystemBlur);
+        // 作れなかった方式も、初期化に失敗した方式も使えない方式として返す
+        assert_eq!(unavailable, [SystemTabbed, SystemMica, SystemAcrylic]);
+        assert_eq!(tried, [SystemTabbed, SystemMica, SystemAcrylic, SystemBlur]);
+    }
+
+    #[test]
+    fn select_falls_back_to_null_when_everything_fails() {
+        use OverlayBackend::*;
+        let chain = CpuBlur.fallback_chain();
+        let (kind, unavailable, tried) = select_with(CpuBlur, chain, &[]);
+        assert_eq!(kind, Null);
+        assert_eq!(unavailable, chain);
+        assert_eq!(tried, chain);
+    }
+
+    #[test]
+    fn select_initializes_the_chosen_backend_once() {
+        let failing = RecordingBackend {
+            fail_init: true,
+            ..RecordingBackend::new(OverlayBackend::GpuBlur)
+        };
+        let chosen = RecordingBackend::new(OverlayBackend::CpuBlur);
+        let (failing_calls, chosen_calls) = (failing.calls.clone(), chosen.calls.clone());
+        let mut candidates = vec![chosen, failing];
+
+        let (backend, unavailable) = select(OverlayBackend::GpuBlur, |_| -> Created {
+            Ok(Box::new(candidates.pop().ok_or("no more candidates")?))
+        });
+        assert_eq!(backend.kind(), OverlayBackend::CpuBlur);
+        assert_eq!(unavailable, [OverlayBackend::GpuBlur]);
+        assert!(matches!(
+            failing_calls.lock().unwrap()[..],
+            [BackendCall::Init]
+        ));
+        assert!(matches!(
+            chosen_calls.lock().unwrap()[..],
+            [BackendCall::Init]
+        ));
+    }
+
+    #[test]
+    fn recording_backend_records_calls_in_order() {
+        let mut backend = RecordingBackend::new(OverlayBackend::GpuBlur);
+        let calls = backend.calls.clone();
+        let mut source = SyntheticFrameSource::new(32, 16);
+        let frame = source.next_frame(None).unwrap().unwrap();
+
+        backend.init().unwrap();
+        backend.resize(32, 16, 1.5);
+        let settings = Settings {
+            blur_mode: BlurMode::DualKawase,
+            ..Settings::default()
+        };
+        backend.set_params(&settings);
+        backend
+            .render_frame(Some((&frame, (0, 0))), &Params::new(10.0))
+            .unwrap();
+        backend.render_frame(None, &Params::new(20.0)).unwrap();
+        backend.shutdown();
+
+        let calls = calls.lock().unwrap();
+        assert!(matches!(
+            calls[..],
+            [
+                BackendCall::Init,
+                BackendCall::Resize {
+                    width: 32,
+                    height: 16,
+                    scale_factor: 1.5,
+                },
+                BackendCall::SetParams(_),
+                BackendCall::RenderFrame {
+                    frame_size: Some((32, 16)),
+                    ..
+                },
+                BackendCall::RenderFrame {
+                    frame_size: None,
+                    ..
+                },
+                BackendCall::Shutdown,
+            ]
+        ));
+        let BackendCall::SetParams(applied) = &calls[2] else {
+            unreachable!();
+        };
+        assert_eq!(applied.blur_mode, BlurMode::DualKawase);
+        let BackendCall::RenderFrame { params, .. } = &calls[4] else {
+            unreachable!();
+        };
+        assert_eq!(params.blur_strength, 20.0);
+    }
+}
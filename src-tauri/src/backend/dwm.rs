@@ -0,0 +1,110 @@
+use super::{BackdropTheme, BlurBackend, CapturedFrame, OverlayBackend};
+use crate::renderer::Params;
+use crate::settings::Settings;
+use tauri::WebviewWindow;
+
+/// DWM に描かせる背景の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Material {
+    Mica,
+    /// Mica Alt（window-vibrancy では tabbed）
+    Tabbed,
+    Acrylic,
+    /// 旧来のブラー
+    Blur,
+}
+
+impl Material {
+    /// 色合いを指定できる素材か（Mica 系は色を OS が壁紙から決める）
+    fn uses_tint(self) -> bool {
+        matches!(self, Material::Acrylic | Material::Blur)
+    }
+}
+
+/// window-vibrancy で DWM の背景効果を付ける（描画は OS の合成に任せる）
+///
+/// 使えない素材は `init` がエラーを返すので、`backend::select` が次の方式に落とす。
+pub struct DwmBackdrop {
+    window: WebviewWindow,
+    material: Material,
+    tint: [u8; 4],
+    theme: BackdropTheme,
+}
+
+impl DwmBackdrop {
+    pub fn new(window: WebviewWindow, material: Material, settings: &Settings) -> Self {
+        Self {
+            window,
+            material,
+            tint: settings.tint,
+            theme: settings.backdrop_theme,
+        }
+    }
+
+    fn apply(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // RGBA: (R, G, B, A) - A は透明度（0=完全透明, 255=不透明）
+        let tint = Some((self.tint[0], self.tint[1], self.tint[2], self.tint[3]));
+        let dark = self.theme.dark();
+        let result = match self.material {
+            Material::Mica => window_vibrancy::apply_mica(&self.window, dark),
+            Material::Tabbed => window_vibrancy::apply_tabbed(&self.window, dark),
+            Material::Acrylic => window_vibrancy::apply_acrylic(&self.window, tint),
+            Material::Blur => window_vibrancy::apply_blur(&self.window, tint),
+        };
+        result.map_err(|e| format!("{:?} failed: {:?}", self.material, e).into())
+    }
+}
+
+impl BlurBackend for DwmBackdrop {
+    fn kind(&self) -> OverlayBackend {
+        match self.material {
+            Material::Mica => OverlayBackend::SystemMica,
+            Material::Tabbed => OverlayBackend::SystemTabbed,
+            Material::Acrylic => OverlayBackend::SystemAcrylic,
+            Material::Blur => OverlayBackend::SystemBlur,
+        }
+    }
+
+    fn init(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.apply()?;
+        tracing::info!("{:?} backdrop applied", self.material);
+        Ok(())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32, _scale_factor: f64) {}
+
+    fn set_params(&mut self, settings: &Settings) {
+        let changed = if self.material.uses_tint() {
+            settings.tint != self.tint
+        } else {
+            settings.backdrop_theme != self.theme
+        };
+        self.tint = settings.tint;
+        self.theme = settings.backdrop_theme;
+        if changed {
+            if let Err(e) = self.apply() {
+                tracing::warn!("Failed to update backdrop: {}", e);
+            }
+        }
+    }
+
+    fn render_frame(
+        &mut self,
+        _frame: Option<CapturedFrame>,
+        _params: &Params,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn needs_capture(&self) -> bool {
+        false
+    }
+
+    fn shutdown(&mut self) {
+        // 残っていると他の方式に切り替えたときに穴の部分までぼかされる
+        let _ = window_vibrancy::clear_acrylic(&self.window);
+        let _ = window_vibrancy::clear_blur(&self.window);
+        let _ = window_vibrancy::clear_mica(&self.window);
+        let _ = window_vibrancy::clear_tabbed(&self.window);
+    }
+}
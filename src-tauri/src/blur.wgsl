@@ -18,45 +18,114 @@ fn vs_main(
 @group(0) @binding(0) var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1) var s_diffuse: sampler;
 
+const MAX_HOLES: u32 = 16u; // renderer.rs の MAX_HOLES と揃えること
+
+// effects.rs の EFFECT_* と揃えること
+const EFFECT_PIXELATE: u32 = 1u;
+const EFFECT_GRAYSCALE: u32 = 2u;
+const EFFECT_DIM: u32 = 4u;
+const EFFECT_TINT: u32 = 8u;
+const EFFECT_NOISE: u32 = 16u;
+const NOISE_SCALE: f32 = 0.25;
+
+struct EffectChain {
+    flags: u32,
+    pixelate_size: f32, // pixels
+    grayscale: f32,
+    dim: f32,
+    tint: vec4<f32>, // linear rgb + strength
+    noise: f32,
+    noise_seed: f32,
+};
+
 struct Params {
-    hole_rect: vec4<f32>, // [left, top, right, bottom] in relative normalized coords
-    blur_strength: f32,
+    holes: array<vec4<f32>, MAX_HOLES>, // [left, top, right, bottom] in relative normalized coords
+    hole_radii: array<vec4<f32>, 4>, // corner radius in pixels, packed 4 per element (MAX_HOLES / 4)
+    resolution: vec2<f32>, // physical pixels
+    hole_count: u32,
+    blur_strength: f32, // used on the CPU side to build the Gaussian kernel
+    feather: f32, // pixels
+    opacity: f32, // fade in/out
+    effects: EffectChain,
 };
 @group(1) @binding(0) var<uniform> params: Params;
 
+// 角丸矩形までの符号付き距離 (内側が負)。mask.rs の sd_rounded_rect と揃えること
+fn sd_rounded_rect(p: vec2<f32>, rect: vec4<f32>, radius: f32) -> f32 {
+    let center = (rect.xy + rect.zw) * 0.5;
+    let half_size = (rect.zw - rect.xy) * 0.5;
+    let r = clamp(radius, 0.0, max(min(half_size.x, half_size.y), 0.0));
+    let q = abs(p - center) - half_size + vec2<f32>(r);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - r;
+}
+
+// 0 = 穴の中で鮮明、1 = 完全にぼかす。mask.rs の edge_mask / hole_mask と揃えること
+fn hole_mask(uv: vec2<f32>) -> f32 {
+    let p = uv * params.resolution;
+    var mask = 1.0;
+    for (var i = 0u; i < min(params.hole_count, MAX_HOLES); i = i + 1u) {
+        let rect = params.holes[i] * params.resolution.xyxy;
+        let d = sd_rounded_rect(p, rect, params.hole_radii[i / 4u][i % 4u]);
+        var m = select(0.0, 1.0, d > 0.0);
+        if (params.feather > 0.0) {
+            let t = clamp(d / params.feather, 0.0, 1.0);
+            m = t * t * (3.0 - 2.0 * t);
+        }
+        mask = min(mask, m);
+    }
+    return mask;
+}
+
+fn has_effect(flag: u32) -> bool {
+    return (params.effects.flags & flag) != 0u;
+}
+
+// モザイク時はブロックの中心をサンプリングする。effects.rs の pixelate_uv と揃えること
+fn pixelate_uv(uv: vec2<f32>) -> vec2<f32> {
+    let block = params.effects.pixelate_size;
+    if (!has_effect(EFFECT_PIXELATE) || block <= 0.0) {
+        return uv;
+    }
+    return (floor(uv * params.resolution / block) + 0.5) * block / params.resolution;
+}
+
+fn noise_hash(pixel: vec2<f32>, seed: f32) -> f32 {
+    return fract(sin(dot(pixel, vec2<f32>(12.9898, 78.233)) + seed) * 43758.547);
+}
+
+// 色に関するステージ。effects.rs の EffectChain::apply と揃えること
+fn apply_effects(color: vec3<f32>, pixel: vec2<f32>) -> vec3<f32> {
+    var rgb = color;
+    if (has_effect(EFFECT_GRAYSCALE)) {
+        let luma = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        rgb = mix(rgb, vec3<f32>(luma), params.effects.grayscale);
+    }
+    if (has_effect(EFFECT_DIM)) {
+        rgb = rgb * (1.0 - params.effects.dim);
+    }
+    if (has_effect(EFFECT_TINT)) {
+        rgb = mix(rgb, params.effects.tint.rgb, params.effects.tint.a);
+    }
+    if (has_effect(EFFECT_NOISE)) {
+        let grain = (noise_hash(pixel, params.effects.noise_seed) - 0.5) * params.effects.noise * NOISE_SCALE;
+        rgb = clamp(rgb + vec3<f32>(grain), vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    return rgb;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let x = in.tex_coords.x;
-    let y = in.tex_coords.y;
+    // ぼかしは gaussian.wgsl / kawase.wgsl で済んでいる。ここでは効果をかけて穴を抜くだけ
+    let color = textureSample(t_diffuse, s_diffuse, pixelate_uv(in.tex_coords));
+    let rgb = apply_effects(color.rgb, floor(in.tex_coords * params.resolution));
 
-    // 穴の範囲チェック (normalized coords)
-    // 浮動小数点の誤差を考慮して 0.001 のマージン
-    if (x >= params.hole_rect.x && x <= params.hole_rect.z &&
-        y >= params.hole_rect.y && y <= params.hole_rect.w) {
+    // 穴の範囲チェック (角丸・フェザー込み)
+    // textureSample は一様な制御フローでしか呼べないので、サンプリング後に判定する
+    let mask = hole_mask(in.tex_coords);
+    if (mask <= 0.0) {
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
 
-    // ガウスぼかしの実装 (9タップ正確版)
-    let spread = params.blur_strength * 0.001;
-    var color = vec4<f32>(0.0);
-    
-    // ガウス重みの係数 (合計が 1.0 になるように調整)
-    let w0 = 0.227027;
-    let w1 = 0.1216216;
-    let w2 = 0.054054;
-    let w3 = 0.016216;
-
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords) * w0;
-    
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(spread, 0.0)) * w1;
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(-spread, 0.0)) * w1;
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(0.0, spread)) * w1;
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(0.0, -spread)) * w1;
-    
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(spread, spread)) * w2;
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(-spread, spread)) * w2;
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(spread, -spread)) * w2;
-    color += textureSample(t_diffuse, s_diffuse, in.tex_coords + vec2<f32>(-spread, -spread)) * w2;
-
-    return vec4<f32>(color.rgb, 1.0);
+    // フェザー部分はアルファで背後の鮮明な画面と混ぜる。フェード中は全体を薄くする
+    return vec4<f32>(rgb, mask * params.opacity);
 }
@@ -1,156 +1,152 @@
-use windows::core::Interface;
-use windows::Graphics::Capture::{
-    Direct3D11CaptureFramePool, GraphicsCaptureItem, GraphicsCaptureSession,
-};
-use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
-use windows::Graphics::DirectX::DirectXPixelFormat;
-use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
-use windows::Win32::Graphics::Direct3D11::*;
-use windows::Win32::System::WinRT::Direct3D11::{
-    CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess,
-};
-
-pub struct CaptureSession {
-    pub item: GraphicsCaptureItem,
-    pub frame_pool: Direct3D11CaptureFramePool,
-    pub session: GraphicsCaptureSession,
-    pub d3d_device: ID3D11Device,
-    pub context: ID3D11DeviceContext,
+#[cfg(test)]
+pub mod synthetic;
+#[cfg(windows)]
+pub mod win32;
+
+/// キャプチャした 1 フレーム
+// 実際のキャプチャは Windows にしか無い
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum Frame<'a> {
+    /// CPU メモリ上の BGRA8 画素（フォールバック経路）
+    Cpu {
+        width: u32,
+        height: u32,
+        /// 1 行のバイト数（パディングを含む）
+        row_pitch: u32,
+        data: &'a [u8],
+    },
+    /// wgpu から直接コピーできるテクスチャ（共有テクスチャ経路。CPU を経由しない）
+    Gpu(&'a wgpu::Texture),
 }
 
-impl CaptureSession {
-    pub fn new(item: GraphicsCaptureItem) -> Result<Self, Box<dyn std::error::Error>> {
-        unsafe {
-            let mut d3d_device: Option<ID3D11Device> = None;
-            let mut context: Option<ID3D11DeviceContext> = None;
-
-            D3D11CreateDevice(
-                None,
-                D3D_DRIVER_TYPE_HARDWARE,
-                windows::Win32::Foundation::HMODULE::default(),
-                D3D11_CREATE_DEVICE_BGRA_SUPPORT,
-                None,
-                D3D11_SDK_VERSION,
-                Some(&mut d3d_device),
-                None,
-                Some(&mut context),
-            )?;
-
-            let d3d_device = d3d_device.unwrap();
-            let context = context.unwrap();
-
-            let dxgi_device: windows::Win32::Graphics::Dxgi::IDXGIDevice = d3d_device.cast()?;
-            let inspectable = CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device)?;
-            let device: IDirect3DDevice = inspectable.cast()?;
-
-            let size = item.Size()?;
-            let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
-                &device,
-                DirectXPixelFormat::B8G8R8A8UIntNormalized,
-                2,
-                size,
-            )?;
-
-            let session = frame_pool.CreateCaptureSession(&item)?;
-            session.StartCapture()?;
-
-            Ok(Self {
-                item,
-                frame_pool,
-                session,
-                d3d_device,
-                context,
-            })
+impl Frame<'_> {
+    pub fn size(&self) -> (u32, u32) {
+        match self {
+            Frame::Cpu { width, height, .. } => (*width, *height),
+            Frame::Gpu(texture) => (texture.width(), texture.height()),
         }
     }
+}
 
-    pub fn capture_frame_to_wgpu(
-        &self,
-        queue: &wgpu::Queue,
-        target_texture: &wgpu::Texture,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        unsafe {
-            let frame = match self.frame_pool.TryGetNextFrame() {
-                Ok(f) => f,
-                Err(e) => return Err(format!("TryGetNextFrame failed: {:?}", e).into()),
-            };
-
-            let surface = match frame.Surface() {
-                Ok(s) => s,
-                Err(e) => return Err(format!("Frame Surface failed: {:?}", e).into()),
-            };
+/// 画面キャプチャの取得元
+///
+/// OS のキャプチャ API の違いを隠す。`next_frame` はレンダースレッドから毎フレーム呼ばれる。
+pub trait FrameSource {
+    /// 前回から新しいフレームが届いていれば返す。届いていなければ `None`
+    ///
+    /// 返したフレームは次に `next_frame` を呼ぶまで有効。`device` が無ければ（CPU で描くとき）
+    /// 必ず `Frame::Cpu` を返す。
+    fn next_frame(
+        &mut self,
+        device: Option<&wgpu::Device>,
+    ) -> Result<Option<Frame<'_>>, Box<dyn std::error::Error>>;
+}
 
-            // WinRT IDirect3DSurface -> Win32 ID3D11Texture2D の変換には
-            // IDirect3DDxgiInterfaceAccess を介す必要がある (E_NOINTERFACE 回避)
-            let interop: IDirect3DDxgiInterfaceAccess = surface.cast()?;
-            let d3d_texture: ID3D11Texture2D = interop.GetInterface()?;
+/// 同じサイズのテクスチャを毎フレーム作り直さずに使い回すためのプール
+///
+/// `T` は OS 側のテクスチャ型。サイズが変わったときだけ作り直す。
+/// 複数枠を順番に使うので、1 フレーム前に書き込んだ枠を読み出している間に次の枠へ書き込める。
+#[cfg(any(windows, test))]
+pub struct TexturePool<T> {
+    slots: Vec<T>,
+    size: Option<(u32, u32)>,
+    capacity: usize,
+    next: usize,
+}
 
-            let mut desc = D3D11_TEXTURE2D_DESC::default();
-            d3d_texture.GetDesc(&mut desc);
+#[cfg(any(windows, test))]
+impl<T> TexturePool<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: Vec::new(),
+            size: None,
+            capacity: capacity.max(1),
+            next: 0,
+        }
+    }
 
-            if desc.Width == 0 || desc.Height == 0 {
-                return Err("Captured texture has 0 size".into());
-            }
+    /// `size` の枠を順番に返す。サイズが変わったら作り直し、足りなければ `create` で追加する
+    pub fn acquire<E>(
+        &mut self,
+        size: (u32, u32),
+        create: impl FnOnce() -> Result<T, E>,
+    ) -> Result<&T, E> {
+        if self.size != Some(size) {
+            self.slots.clear();
+            self.size = Some(size);
+            self.next = 0;
+        }
 
-            let staging_desc = D3D11_TEXTURE2D_DESC {
-                Width: desc.Width,
-                Height: desc.Height,
-                MipLevels: 1,
-                ArraySize: 1,
-                Format: desc.Format,
-                SampleDesc: desc.SampleDesc,
-                Usage: D3D11_USAGE_STAGING,
-                BindFlags: 0,
-                CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
-                MiscFlags: 0,
-            };
+        let index = self.next;
+        if index == self.slots.len() {
+            self.slots.push(create()?);
+        }
+        self.next = (index + 1) % self.capacity;
+        Ok(&self.slots[index])
+    }
 
-            let mut staging_texture: Option<ID3D11Texture2D> = None;
-            self.d3d_device
-                .CreateTexture2D(&staging_desc, None, Some(&mut staging_texture))?;
-            let staging_texture = staging_texture.ok_or("Failed to create staging texture")?;
+    /// これまでに作った枠の数
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
 
-            // Resource にキャストして Copy/Map に渡す
-            let src_resource: ID3D11Resource = d3d_texture.cast()?;
-            let dst_resource: ID3D11Resource = staging_texture.cast()?;
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
 
-            self.context.CopyResource(&dst_resource, &src_resource);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
-            self.context
-                .Map(&dst_resource, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+    /// 作った順に番号を振る `create`
+    fn counter(created: &mut u32) -> impl FnOnce() -> Result<u32, ()> + '_ {
+        move || {
+            *created += 1;
+            Ok(*created)
+        }
+    }
 
-            let row_pitch = mapped.RowPitch;
-            let data_size = (desc.Height * row_pitch) as usize;
-            let data = std::slice::from_raw_parts(mapped.pData as *const u8, data_size);
+    #[test]
+    fn pool_reuses_slots_in_order() {
+        let mut pool = TexturePool::new(2);
+        let mut created = 0;
+        let acquired: Vec<u32> = (0..5)
+            .map(|_| *pool.acquire((64, 32), counter(&mut created)).unwrap())
+            .collect();
+        assert_eq!(acquired, [1, 2, 1, 2, 1]);
+        assert_eq!(created, 2);
+        assert_eq!(pool.len(), 2);
+    }
 
-            // テクスチャのサイズがキャプチャデータと異なる場合は、キャプチャデータ側に合わせる
-            // (本来は Renderer 側でキャプチャサイズに合わせてリサイズしておくのが理想)
-            let copy_width = desc.Width.min(target_texture.width());
-            let copy_height = desc.Height.min(target_texture.height());
+    #[test]
+    fn pool_recreates_on_resize() {
+        let mut pool = TexturePool::new(2);
+        let mut created = 0;
+        pool.acquire((64, 32), counter(&mut created)).unwrap();
+        pool.acquire((64, 32), counter(&mut created)).unwrap();
+        assert_eq!(*pool.acquire((128, 32), counter(&mut created)).unwrap(), 3);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(*pool.acquire((128, 32), counter(&mut created)).unwrap(), 4);
+        assert_eq!(*pool.acquire((128, 32), counter(&mut created)).unwrap(), 3);
+    }
 
-            queue.write_texture(
-                wgpu::TexelCopyTextureInfo {
-                    texture: target_texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                    aspect: wgpu::TextureAspect::All,
-                },
-                data,
-                wgpu::TexelCopyBufferLayout {
-                    offset: 0,
-                    bytes_per_row: Some(row_pitch),
-                    rows_per_image: Some(desc.Height),
-                },
-                wgpu::Extent3d {
-                    width: copy_width,
-                    height: copy_height,
-                    depth_or_array_layers: 1,
-                },
-            );
+    #[test]
+    fn pool_failure_does_not_advance() {
+        let mut pool = TexturePool::<u32>::new(2);
+        assert!(pool.acquire((8, 8), || Err("out of memory")).is_err());
+        assert!(pool.is_empty());
+        assert_eq!(pool.acquire((8, 8), || Ok::<_, ()>(7)), Ok(&7));
+        assert_eq!(pool.len(), 1);
+    }
 
-            self.context.Unmap(&dst_resource, 0);
-        }
-        Ok(())
+    #[test]
+    fn pool_capacity_is_at_least_one() {
+        let mut pool = TexturePool::new(0);
+        assert_eq!(pool.acquire((1, 1), || Ok::<_, ()>(1)), Ok(&1));
+        // 枠が 1 つなら毎回同じ枠を返し、作り直さない
+        assert_eq!(pool.acquire((1, 1), || Ok::<_, ()>(2)), Ok(&1));
     }
 }
@@ -0,0 +1,123 @@
+use super::{Frame, FrameSource};
+
+/// テスト用の合成フレーム（OS のキャプチャ API を使わない）
+///
+/// 呼ばれるたびに模様をずらした BGRA8 画像を CPU フレームとして返す。
+/// 行末にはパディングを入れて、実際のキャプチャと同じく `row_pitch` を考慮させる。
+pub struct SyntheticFrameSource {
+    pub width: u32,
+    pub height: u32,
+    /// これまでに返したフレーム数
+    pub frame_index: u32,
+    row_pitch: u32,
+    pixels: Vec<u8>,
+}
+
+/// 1 行あたりのバイト数を揃える単位（D3D11 の RowPitch と同じく 256 に揃える）
+const ROW_ALIGNMENT: u32 = 256;
+
+impl SyntheticFrameSource {
+    pub fn new(width: u32, height: u32) -> Self {
+        let row_pitch = (width * 4).div_ceil(ROW_ALIGNMENT) * ROW_ALIGNMENT;
+        Self {
+            width,
+            height,
+            frame_index: 0,
+            row_pitch,
+            pixels: vec![0; (row_pitch * height) as usize],
+        }
+    }
+
+    /// `frame_index` フレーム目の (x, y) の画素（BGRA）
+    pub fn pixel(&self, x: u32, y: u32, frame_index: u32) -> [u8; 4] {
+        let shift = frame_index.wrapping_mul(8);
+        let checker = if ((x + shift) / 16 + y / 16).is_multiple_of(2) {
+            255
+        } else {
+            0
+        };
+        [
+            (x.wrapping_add(shift) % 256) as u8,
+            (y % 256) as u8,
+            checker,
+            255,
+        ]
+    }
+}
+
+impl FrameSource for SyntheticFrameSource {
+    fn next_frame(
+        &mut self,
+        _device: Option<&wgpu::Device>,
+    ) -> Result<Option<Frame<'_>>, Box<dyn std::error::Error>> {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let offset = (y * self.row_pitch + x * 4) as usize;
+                let pixel = self.pixel(x, y, self.frame_index);
+                self.pixels[offset..offset + 4].copy_from_slice(&pixel);
+            }
+        }
+        self.frame_index += 1;
+
+        Ok(Some(Frame::Cpu {
+            width: self.width,
+            height: self.height,
+            row_pitch: self.row_pitch,
+            data: &self.pixels,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rows_are_padded() {
+        let mut source = SyntheticFrameSource::new(70, 3);
+        let Some(Frame::Cpu {
+            width,
+            height,
+            row_pitch,
+            data,
+        }) = source.next_frame(None).unwrap()
+        else {
+            panic!("expected a CPU frame");
+        };
+        assert_eq!((width, height), (70, 3));
+        assert_eq!(row_pitch, 512);
+        assert_eq!(data.len(), 512 * 3);
+    }
+
+    #[test]
+    fn frame_matches_pixel_pattern() {
+        let mut source = SyntheticFrameSource::new(40, 20);
+        for frame_index in 0..3 {
+            let expected: Vec<[u8; 4]> = (0..20)
+                .flat_map(|y| (0..40).map(move |x| (x, y)))
+                .map(|(x, y)| source.pixel(x, y, frame_index))
+                .collect();
+            let Some(Frame::Cpu {
+                row_pitch, data, ..
+            }) = source.next_frame(None).unwrap()
+            else {
+                panic!("expected a CPU frame");
+            };
+            for (i, pixel) in expected.iter().enumerate() {
+                let (x, y) = (i as u32 % 40, i as u32 / 40);
+                let offset = (y * row_pitch + x * 4) as usize;
+                assert_eq!(&data[offset..offset + 4], pixel, "({x}, {y})");
+            }
+        }
+        assert_eq!(source.frame_index, 3);
+    }
+
+    #[test]
+    fn pattern_moves_between_frames() {
+        let source = SyntheticFrameSource::new(64, 64);
+        // 1 フレームで 8 ピクセル右へずれる
+        assert_eq!(source.pixel(8, 5, 0), source.pixel(0, 5, 1));
+        assert_ne!(source.pixel(0, 5, 0), source.pixel(0, 5, 1));
+        assert_eq!(source.pixel(3, 7, 0)[3], 255);
+    }
+}
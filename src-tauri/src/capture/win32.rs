@@ -0,0 +1,394 @@
+use super::{Frame, FrameSource, TexturePool};
+use windows::core::{Interface, PCWSTR};
+use windows::Graphics::Capture::{
+    Direct3D11CaptureFramePool, GraphicsCaptureItem, GraphicsCaptureSession,
+};
+use windows::Graphics::DirectX::Direct3D11::IDirect3DDevice;
+use windows::Graphics::DirectX::DirectXPixelFormat;
+use windows::Win32::Foundation::{CloseHandle, GENERIC_ALL};
+use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_HARDWARE;
+use windows::Win32::Graphics::Direct3D11::*;
+use windows::Win32::Graphics::Direct3D12::{
+    ID3D12CommandQueue, ID3D12Device, ID3D12Fence, ID3D12Resource,
+};
+use windows::Win32::Graphics::Dxgi::Common::DXGI_FORMAT_B8G8R8A8_UNORM;
+use windows::Win32::Graphics::Dxgi::{
+    IDXGIResource1, DXGI_SHARED_RESOURCE_READ, DXGI_SHARED_RESOURCE_WRITE,
+};
+use windows::Win32::Graphics::Gdi::HMONITOR;
+use windows::Win32::System::WinRT::Direct3D11::{
+    CreateDirect3D11DeviceFromDXGIDevice, IDirect3DDxgiInterfaceAccess,
+};
+use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
+
+/// モニター全体をキャプチャ対象にする
+pub fn monitor_capture_item(monitor: HMONITOR) -> windows::core::Result<GraphicsCaptureItem> {
+    let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()?;
+    unsafe { interop.CreateForMonitor(monitor) }
+}
+
+/// フォールバック経路で使い回すステージングテクスチャの数
+const STAGING_POOL_SIZE: usize = 2;
+
+/// D3D11（キャプチャ側）と D3D12（wgpu 側）で共有するテクスチャとフェンス
+struct SharedTexture {
+    d3d11_texture: ID3D11Texture2D,
+    texture: wgpu::Texture,
+    d3d11_fence: ID3D11Fence,
+    d3d12_fence: ID3D12Fence,
+    fence_value: u64,
+}
+
+/// Windows Graphics Capture によるモニターのキャプチャ
+///
+/// wgpu が D3D12 で動いていれば共有テクスチャで GPU 上のまま受け渡す。
+/// それ以外（Vulkan など）や共有に失敗した場合は、使い回しのステージングテクスチャで CPU に読み出す。
+pub struct CaptureSession {
+    pub item: GraphicsCaptureItem,
+    pub frame_pool: Direct3D11CaptureFramePool,
+    pub session: GraphicsCaptureSession,
+    pub d3d_device: ID3D11Device,
+    pub context: ID3D11DeviceContext,
+    shared: Option<SharedTexture>,
+    /// 一度共有に失敗したら以降はフォールバック経路だけを使う
+    shared_unsupported: bool,
+    staging: TexturePool<ID3D11Texture2D>,
+    /// コピーを積んだだけでまだ Map していないステージングテクスチャ（次の呼び出しで Map する）
+    pending: Option<ID3D11Texture2D>,
+    /// 前回 Map したまま返したステージングテクスチャ（次の呼び出しで Unmap する）
+    mapped: Option<ID3D11Texture2D>,
+}
+
+impl CaptureSession {
+    pub fn new(item: GraphicsCaptureItem) -> Result<Self, Box<dyn std::error::Error>> {
+        unsafe {
+            let mut d3d_device: Option<ID3D11Device> = None;
+            let mut context: Option<ID3D11DeviceContext> = None;
+
+            D3D11CreateDevice(
+                None,
+                D3D_DRIVER_TYPE_HARDWARE,
+                windows::Win32::Foundation::HMODULE::default(),
+                D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                None,
+                D3D11_SDK_VERSION,
+                Some(&mut d3d_device),
+                None,
+                Some(&mut context),
+            )?;
+
+            let d3d_device = d3d_device.unwrap();
+            let context = context.unwrap();
+
+            let dxgi_device: windows::Win32::Graphics::Dxgi::IDXGIDevice = d3d_device.cast()?;
+            let inspectable = CreateDirect3D11DeviceFromDXGIDevice(&dxgi_device)?;
+            let device: IDirect3DDevice = inspectable.cast()?;
+
+            let size = item.Size()?;
+            let frame_pool = Direct3D11CaptureFramePool::CreateFreeThreaded(
+                &device,
+                DirectXPixelFormat::B8G8R8A8UIntNormalized,
+                2,
+                size,
+            )?;
+
+            let session = frame_pool.CreateCaptureSession(&item)?;
+            session.StartCapture()?;
+
+            Ok(Self {
+                item,
+                frame_pool,
+                session,
+                d3d_device,
+                context,
+                shared: None,
+                shared_unsupported: false,
+                staging: TexturePool::new(STAGING_POOL_SIZE),
+                pending: None,
+                mapped: None,
+            })
+        }
+    }
+
+    /// キャプチャと同じサイズの共有テクスチャを作り、wgpu の D3D12 デバイスで開く
+    unsafe fn create_shared_texture(
+        &self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Result<SharedTexture, Box<dyn std::error::Error>> {
+        let hal_device = device
+            .as_hal::<wgpu::hal::api::Dx12>()
+            .ok_or("wgpu is not running on D3D12")?;
+        // wgpu-hal は別バージョンの windows クレートの型を使うので、生の COM ポインタを借りて読み替える
+        // （参照カウントは hal 側が持ったまま）
+        let raw_device = windows_core_058::Interface::as_raw(hal_device.raw_device());
+        let d3d12_device =
+            ID3D12Device::from_raw_borrowed(&raw_device).ok_or("D3D12 device is null")?;
+
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+            SampleDesc: windows::Win32::Graphics::Dxgi::Common::DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Usage: D3D11_USAGE_DEFAULT,
+            BindFlags: D3D11_BIND_SHADER_RESOURCE.0 as u32,
+            CPUAccessFlags: 0,
+            MiscFlags: (D3D11_RESOURCE_MISC_SHARED.0 | D3D11_RESOURCE_MISC_SHARED_NTHANDLE.0)
+                as u32,
+        };
+        let mut d3d11_texture: Option<ID3D11Texture2D> = None;
+        self.d3d_device
+            .CreateTexture2D(&desc, None, Some(&mut d3d11_texture))?;
+        let d3d11_texture = d3d11_texture.ok_or("Failed to create shared texture")?;
+
+        let handle = d3d11_texture.cast::<IDXGIResource1>()?.CreateSharedHandle(
+            None,
+            DXGI_SHARED_RESOURCE_READ.0 | DXGI_SHARED_RESOURCE_WRITE.0,
+            PCWSTR::null(),
+        )?;
+        let mut resource: Option<ID3D12Resource> = None;
+        let opened = d3d12_device.OpenSharedHandle(handle, &mut resource);
+        let _ = CloseHandle(handle);
+        opened?;
+        let resource = resource.ok_or("Failed to open shared texture")?;
+
+        // D3D11 のコピー完了を D3D12 のキューに待たせるための共有フェンス
+        let mut d3d11_fence: Option<ID3D11Fence> = None;
+        self.d3d_device.cast::<ID3D11Device5>()?.CreateFence(
+            0,
+            D3D11_FENCE_FLAG_SHARED,
+            &mut d3d11_fence,
+        )?;
+        let d3d11_fence = d3d11_fence.ok_or("Failed to create shared fence")?;
+        let fence_handle = d3d11_fence.CreateSharedHandle(None, GENERIC_ALL.0, PCWSTR::null())?;
+        let mut d3d12_fence: Option<ID3D12Fence> = None;
+        let opened = d3d12_device.OpenSharedHandle(fence_handle, &mut d3d12_fence);
+        let _ = CloseHandle(fence_handle);
+        opened?;
+        let d3d12_fence = d3d12_fence.ok_or("Failed to open shared fence")?;
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let format = wgpu::TextureFormat::Bgra8Unorm;
+        // 同じく COM ポインタを hal 側の型へ読み替える（参照カウントごと渡す）
+        let hal_texture = wgpu::hal::dx12::Device::texture_from_raw(
+            windows_core_058::Interface::from_raw(resource.into_raw()),
+            format,
+            wgpu::TextureDimension::D2,
+            size,
+            1,
+            1,
+        );
+        let texture = device.create_texture_from_hal::<wgpu::hal::api::Dx12>(
+            hal_texture,
+            &wgpu::TextureDescriptor {
+                label: Some("shared_capture_texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            },
+        );
+
+        Ok(SharedTexture {
+            d3d11_texture,
+            texture,
+            d3d11_fence,
+            d3d12_fence,
+            fence_value: 0,
+        })
+    }
+
+    /// 共有テクスチャへ GPU 上でコピーする。共有できない環境なら `Ok(false)`
+    unsafe fn copy_to_shared(
+        &mut self,
+        device: &wgpu::Device,
+        source: &ID3D11Resource,
+        width: u32,
+        height: u32,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        if self.shared_unsupported {
+            return Ok(false);
+        }
+        let resized = self
+            .shared
+            .as_ref()
+            .is_none_or(|s| s.texture.width() != width || s.texture.height() != height);
+        if resized {
+            match self.create_shared_texture(device, width, height) {
+                Ok(shared) => self.shared = Some(shared),
+                Err(e) => {
+                    tracing::warn!(
+                        "Shared capture texture unavailable, using staging copy: {}",
+                        e
+                    );
+                    self.shared = None;
+                    self.shared_unsupported = true;
+                    return Ok(false);
+                }
+            }
+        }
+        let Some(shared) = self.shared.as_mut() else {
+            return Ok(false);
+        };
+
+        self.context
+            .CopyResource(&shared.d3d11_texture.cast::<ID3D11Resource>()?, source);
+        shared.fence_value += 1;
+        self.context
+            .cast::<ID3D11DeviceContext4>()?
+            .Signal(&shared.d3d11_fence, shared.fence_value)?;
+        self.context.Flush();
+
+        // wgpu の以降の提出がコピー完了を待つよう、D3D12 のキューに Wait を積む
+        let hal_device = device
+            .as_hal::<wgpu::hal::api::Dx12>()
+            .ok_or("wgpu is not running on D3D12")?;
+        let raw_queue = windows_core_058::Interface::as_raw(hal_device.raw_queue());
+        let queue =
+            ID3D12CommandQueue::from_raw_borrowed(&raw_queue).ok_or("D3D12 queue is null")?;
+        queue.Wait(&shared.d3d12_fence, shared.fence_value)?;
+        Ok(true)
+    }
+
+    /// ステージングテクスチャへのコピーを積む（フォールバック経路）
+    ///
+    /// コピーの直後に Map すると GPU の完了を待って止まるので、ここでは Map せず
+    /// 次の呼び出しまで `pending` に置いておく。
+    unsafe fn copy_to_staging(
+        &mut self,
+        source: &ID3D11Resource,
+        desc: &D3D11_TEXTURE2D_DESC,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let d3d_device = &self.d3d_device;
+        let staging = self
+            .staging
+            .acquire((desc.Width, desc.Height), || {
+                let staging_desc = D3D11_TEXTURE2D_DESC {
+                    Width: desc.Width,
+                    Height: desc.Height,
+                    MipLevels: 1,
+                    ArraySize: 1,
+                    Format: desc.Format,
+                    SampleDesc: desc.SampleDesc,
+                    Usage: D3D11_USAGE_STAGING,
+                    BindFlags: 0,
+                    CPUAccessFlags: D3D11_CPU_ACCESS_READ.0 as u32,
+                    MiscFlags: 0,
+                };
+                let mut staging_texture: Option<ID3D11Texture2D> = None;
+                d3d_device.CreateTexture2D(&staging_desc, None, Some(&mut staging_texture))?;
+                staging_texture
+                    .ok_or_else(|| windows::core::Error::from(windows::Win32::Foundation::E_FAIL))
+            })?
+            .clone();
+
+        self.context
+            .CopyResource(&staging.cast::<ID3D11Resource>()?, source);
+        self.pending = Some(staging);
+        Ok(())
+    }
+
+    /// 前の呼び出しでコピーを積んだステージングテクスチャを Map して CPU フレームにする
+    unsafe fn map_staging(
+        &mut self,
+        staging: ID3D11Texture2D,
+    ) -> Result<Frame<'_>, Box<dyn std::error::Error>> {
+        let mut desc = D3D11_TEXTURE2D_DESC::default();
+        staging.GetDesc(&mut desc);
+
+        let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+        self.context.Map(
+            &staging.cast::<ID3D11Resource>()?,
+            0,
+            D3D11_MAP_READ,
+            0,
+            Some(&mut mapped),
+        )?;
+        self.mapped = Some(staging);
+
+        let data_size = (desc.Height * mapped.RowPitch) as usize;
+        Ok(Frame::Cpu {
+            width: desc.Width,
+            height: desc.Height,
+            row_pitch: mapped.RowPitch,
+            data: std::slice::from_raw_parts(mapped.pData as *const u8, data_size),
+        })
+    }
+
+    /// 前回返した CPU フレームの Map を解除する
+    unsafe fn unmap_previous(&mut self) {
+        if let Some(staging) = self.mapped.take() {
+            if let Ok(resource) = staging.cast::<ID3D11Resource>() {
+                self.context.Unmap(&resource, 0);
+            }
+        }
+    }
+}
+
+impl FrameSource for CaptureSession {
+    fn next_frame(
+        &mut self,
+        device: Option<&wgpu::Device>,
+    ) -> Result<Option<Frame<'_>>, Box<dyn std::error::Error>> {
+        unsafe {
+            self.unmap_previous();
+
+            // CPU 経路では前の呼び出しでコピーを積んだフレームを返す（1 フレーム遅れる代わりに待たない）
+            let previous = self.pending.take();
+
+            // 新しいフレームが無ければ TryGetNextFrame は失敗する
+            if let Ok(frame) = self.frame_pool.TryGetNextFrame() {
+                let surface = match frame.Surface() {
+                    Ok(s) => s,
+                    Err(e) => return Err(format!("Frame Surface failed: {:?}", e).into()),
+                };
+
+                // WinRT IDirect3DSurface -> Win32 ID3D11Texture2D の変換には
+                // IDirect3DDxgiInterfaceAccess を介す必要がある (E_NOINTERFACE 回避)
+                let interop: IDirect3DDxgiInterfaceAccess = surface.cast()?;
+                let d3d_texture: ID3D11Texture2D = interop.GetInterface()?;
+
+                let mut desc = D3D11_TEXTURE2D_DESC::default();
+                d3d_texture.GetDesc(&mut desc);
+
+                if desc.Width == 0 || desc.Height == 0 {
+                    return Err("Captured texture has 0 size".into());
+                }
+
+                let src_resource: ID3D11Resource = d3d_texture.cast()?;
+                if let Some(device) = device {
+                    if self.copy_to_shared(device, &src_resource, desc.Width, desc.Height)? {
+                        let shared = self.shared.as_ref().ok_or("Shared texture missing")?;
+                        return Ok(Some(Frame::Gpu(&shared.texture)));
+                    }
+                }
+                self.copy_to_staging(&src_resource, &desc)?;
+            }
+
+            // 最初のフレームはまだコピー中なので、次の呼び出しで返す
+            match previous {
+                Some(staging) => self.map_staging(staging).map(Some),
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        unsafe { self.unmap_previous() };
+    }
+}
@@ -0,0 +1,228 @@
+//! フロントエンドから `invoke` で呼ぶコマンド
+//!
+//! 状態が変わったときは次のイベントでフロントエンドに知らせる。
+//! - `settings-changed`: 反映後の `Settings`
+//! - `settings-error`: 設定ファイルを反映できなかった理由
+//! - `overlay-backend-changed`: 実際に使っている `OverlayBackend`（描画スレッドが選び終えたときに送る）
+//! - `overlay-visibility-changed`: オーバーレイが表示中かどうか
+//! - `active-window-changed`: 穴を開けるウィンドウ（`WindowInfo` か `null`）
+
+use crate::backend::{BackdropStatus, BackdropTheme, OverlayBackend};
+use crate::effects::Effect;
+use crate::logging::{self, LogLevel};
+use crate::settings::{Settings, ValidationError};
+use crate::window_manager::{self, WindowInfo};
+use crate::OverlayState;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// コマンドのエラー。JSON では `{ "kind": "invalid", "errors": [...] }` のようになる
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandError {
+    /// 設定値が検証を通らなかった
+    Invalid { errors: Vec<ValidationError> },
+    /// 対象（ウィンドウなど）が見つからない
+    NotFound { message: String },
+    /// OS や Tauri の呼び出しに失敗した
+    Failed { message: String },
+}
+
+impl CommandError {
+    fn failed(message: impl ToString) -> Self {
+        CommandError::Failed {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<tauri::Error> for CommandError {
+    fn from(e: tauri::Error) -> Self {
+        CommandError::failed(e)
+    }
+}
+
+type CommandResult<T> = Result<T, CommandError>;
+
+/// モニターの情報（座標・サイズは物理ピクセル）
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorInfo {
+    pub name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    pub primary: bool,
+}
+
+/// 検証・反映・保存をまとめて行い、反映後の設定を返す
+pub fn commit_settings(app: &AppHandle, settings: Settings) -> CommandResult<Settings> {
+    settings
+        .validate()
+        .map_err(|errors| CommandError::Invalid { errors })?;
+    // 反映に一部失敗しても（ショートカットの衝突など）反映できた分は保存する
+    let applied = crate::apply_settings(app, settings.clone());
+    crate::save_settings(app, &settings);
+    applied.map_err(CommandError::failed)?;
+    Ok(settings)
+}
+
+/// フロントエンドのログを Rust 側のログに混ぜる（`source` には送り元のウィンドウのラベルが付く）
+#[tauri::command]
+pub fn js_log(window: WebviewWindow, message: String, level: Option<LogLevel>) {
+    logging::frontend(window.label(), level.unwrap_or_default(), &message);
+}
+
+/// ログの出力レベルを変えて保存する
+#[tauri::command]
+pub fn set_log_level(
+    app: AppHandle,
+    state: tauri::State<OverlayState>,
+    level: LogLevel,
+) -> CommandResult<Settings> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.log_level = level;
+    commit_settings(&app, settings)
+}
+
+#[tauri::command]
+pub fn open_settings(app: AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+#[tauri::command]
+pub fn get_overlay_backend(state: tauri::State<OverlayState>) -> OverlayBackend {
+    *state.backend.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn set_overlay_backend(app: AppHandle, backend: OverlayBackend) -> CommandResult<()> {
+    crate::switch_overlay_backend(&app, backend).map_err(CommandError::failed)
+}
+
+/// 実際に適用された描画方式と、この環境で使えなかった方式（設定 UI で選べなくする）
+#[tauri::command]
+pub fn get_backdrop_status(app: AppHandle) -> BackdropStatus {
+    crate::backdrop_status(&app)
+}
+
+/// Acrylic と旧来のブラーの背景色（RGBA。A が濃さ）
+#[tauri::command]
+pub fn set_backdrop_tint(
+    app: AppHandle,
+    state: tauri::State<OverlayState>,
+    tint: [u8; 4],
+) -> CommandResult<Settings> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.tint = tint;
+    commit_settings(&app, settings)
+}
+
+/// Mica と Mica Alt を明るい色・暗い色のどちらで描かせるか
+#[tauri::command]
+pub fn set_backdrop_theme(
+    app: AppHandle,
+    state: tauri::State<OverlayState>,
+    theme: BackdropTheme,
+) -> CommandResult<Settings> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.backdrop_theme = theme;
+    commit_settings(&app, settings)
+}
+
+/// 穴を開けるウィンドウ（ルールで除外されるものやオーバーレイ自身は飛ばす）
+#[tauri::command]
+pub fn get_active_window(app: AppHandle, state: tauri::State<OverlayState>) -> Option<WindowInfo> {
+    let rules = state.rules.lock().unwrap().clone();
+    window_manager::get_active_window_info(&rules, &crate::overlay_handles(&app))
+}
+
+#[tauri::command]
+pub fn get_settings(state: tauri::State<OverlayState>) -> Settings {
+    state.settings.lock().unwrap().clone()
+}
+
+/// 設定をまるごと置き換えて保存する
+#[tauri::command]
+pub fn update_settings(app: AppHandle, settings: Settings) -> CommandResult<Settings> {
+    commit_settings(&app, settings)
+}
+
+#[tauri::command]
+pub fn set_blur_strength(
+    app: AppHandle,
+    state: tauri::State<OverlayState>,
+    strength: f32,
+) -> CommandResult<Settings> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.blur_strength = strength;
+    commit_settings(&app, settings)
+}
+
+/// 効果を有効化（同じ種類があれば置き換え）または無効化する
+#[tauri::command]
+pub fn set_effect(
+    app: AppHandle,
+    state: tauri::State<OverlayState>,
+    effect: Effect,
+    enabled: bool,
+) -> CommandResult<Settings> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    let kind = std::mem::discriminant(&effect);
+    let existing = settings
+        .effects
+        .iter()
+        .position(|e| std::mem::discriminant(e) == kind);
+    match (existing, enabled) {
+        (Some(i), true) => settings.effects[i] = effect,
+        (Some(i), false) => {
+            settings.effects.remove(i);
+        }
+        (None, true) => settings.effects.push(effect),
+        (None, false) => {}
+    }
+    commit_settings(&app, settings)
+}
+
+/// オーバーレイの表示を切り替え、切り替え後に表示中かどうかを返す
+#[tauri::command]
+pub fn toggle_overlay(app: AppHandle) -> CommandResult<bool> {
+    crate::toggle_overlay(&app).ok_or_else(|| CommandError::NotFound {
+        message: "no overlay window".to_string(),
+    })
+}
+
+/// 表示中のウィンドウを最前面から順に返す（オーバーレイ自身は除く）
+#[tauri::command]
+pub fn list_windows(app: AppHandle) -> Vec<WindowInfo> {
+    let exclude = crate::overlay_handles(&app);
+    window_manager::list_windows()
+        .into_iter()
+        .filter(|w| !exclude.contains(&w.hwnd))
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_monitors(app: AppHandle) -> CommandResult<Vec<MonitorInfo>> {
+    let primary = app.primary_monitor()?;
+    let monitors = app
+        .available_monitors()?
+        .into_iter()
+        .map(|m| MonitorInfo {
+            name: m.name().cloned(),
+            x: m.position().x,
+            y: m.position().y,
+            width: m.size().width,
+            height: m.size().height,
+            scale_factor: m.scale_factor(),
+            primary: primary
+                .as_ref()
+                .is_some_and(|p| p.name() == m.name() && p.position() == m.position()),
+        })
+        .collect();
+    Ok(monitors)
+}
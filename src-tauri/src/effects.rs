@@ -0,0 +1,148 @@
+//! ぼかしの後段にかける効果（モザイク・減光・グレースケール・単色・ノイズ）
+//!
+//! 各ステージは blur.wgsl の `fs_main` で次の順に適用される。
+//! ぼかし → モザイク → グレースケール → 減光 → 単色 → ノイズ
+//! CPU 側の `EffectChain::apply` / `pixelate_uv` は同じ計算の参照実装。
+
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+
+/// `EffectChain::flags` のビット（blur.wgsl の定数と揃えること）
+pub const EFFECT_PIXELATE: u32 = 1 << 0;
+pub const EFFECT_GRAYSCALE: u32 = 1 << 1;
+pub const EFFECT_DIM: u32 = 1 << 2;
+pub const EFFECT_TINT: u32 = 1 << 3;
+pub const EFFECT_NOISE: u32 = 1 << 4;
+
+/// 設定ファイルに書く効果の指定
+///
+/// JSON では `{ "type": "pixelate", "block_size": 16 }` のように書く。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    /// モザイク。ぼかしと違い元に戻せないので画面共有向け
+    Pixelate {
+        /// ブロックの一辺（論理ピクセル）
+        block_size: f32,
+    },
+    /// 彩度を落とす（0 = そのまま、1 = 完全なグレースケール）
+    Grayscale { amount: f32 },
+    /// 暗くする（0 = そのまま、1 = 真っ黒）
+    Dim { amount: f32 },
+    /// 単色で塗る。アルファが塗りの強さ
+    Tint { color: [u8; 4] },
+    /// フィルムグレイン（0 = なし、1 = 最大）
+    Noise { amount: f32 },
+}
+
+/// シェーダーに渡す効果のパラメータ（`Params::effects`）
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct EffectChain {
+    /// 有効なステージ（`EFFECT_*` のビット和）
+    pub flags: u32,
+    /// モザイクのブロックの一辺（物理ピクセル）
+    pub pixelate_size: f32,
+    pub grayscale: f32,
+    pub dim: f32,
+    /// 線形 RGB と塗りの強さ
+    pub tint: [f32; 4],
+    pub noise: f32,
+    /// ノイズのパターンを毎フレーム変えるためのシード
+    pub noise_seed: f32,
+    pub _padding: [f32; 2],
+}
+
+impl EffectChain {
+    /// 設定の効果一覧から組み立てる。同じ種類が複数あれば後のものが優先される
+    ///
+    /// モザイクのブロックサイズは `scale_factor` を掛けて物理ピクセルに変換する。
+    pub fn from_effects(effects: &[Effect], scale_factor: f64) -> Self {
+        let mut chain = Self::default();
+        for effect in effects {
+            match *effect {
+                Effect::Pixelate { block_size } => {
+                    chain.flags |= EFFECT_PIXELATE;
+                    chain.pixelate_size = (block_size * scale_factor as f32).max(1.0);
+                }
+                Effect::Grayscale { amount } => {
+                    chain.flags |= EFFECT_GRAYSCALE;
+                    chain.grayscale = amount.clamp(0.0, 1.0);
+                }
+                Effect::Dim { amount } => {
+                    chain.flags |= EFFECT_DIM;
+                    chain.dim = amount.clamp(0.0, 1.0);
+                }
+                Effect::Tint { color } => {
+                    let [r, g, b, a] = color;
+                    chain.flags |= EFFECT_TINT;
+                    chain.tint = [
+                        srgb_to_linear(r),
+                        srgb_to_linear(g),
+                        srgb_to_linear(b),
+                        a as f32 / 255.0,
+                    ];
+                }
+                Effect::Noise { amount } => {
+                    chain.flags |= EFFECT_NOISE;
+                    chain.noise = amount.clamp(0.0, 1.0);
+                }
+            }
+        }
+        chain
+    }
+
+    fn has(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// モザイクをかけるとき、正規化座標 `uv` の代わりにサンプリングする座標（ブロックの中心）
+    pub fn pixelate_uv(&self, uv: [f32; 2], resolution: [f32; 2]) -> [f32; 2] {
+        if !self.has(EFFECT_PIXELATE) || self.pixelate_size <= 0.0 {
+            return uv;
+        }
+        let block = self.pixelate_size;
+        [0, 1].map(|i| ((uv[i] * resolution[i] / block).floor() + 0.5) * block / resolution[i])
+    }
+
+    /// 色に関するステージを線形 RGB に適用する。`pixel` はノイズの種に使う物理ピクセル座標
+    pub fn apply(&self, rgb: [f32; 3], pixel: [f32; 2]) -> [f32; 3] {
+        let mut rgb = rgb;
+        if self.has(EFFECT_GRAYSCALE) {
+            let luma = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+            rgb = rgb.map(|c| c + (luma - c) * self.grayscale);
+        }
+        if self.has(EFFECT_DIM) {
+            rgb = rgb.map(|c| c * (1.0 - self.dim));
+        }
+        if self.has(EFFECT_TINT) {
+            let a = self.tint[3];
+            rgb = [0, 1, 2].map(|i| rgb[i] + (self.tint[i] - rgb[i]) * a);
+        }
+        if self.has(EFFECT_NOISE) {
+            let grain = (noise_hash(pixel, self.noise_seed) - 0.5) * self.noise * NOISE_SCALE;
+            rgb = rgb.map(|c| (c + grain).clamp(0.0, 1.0));
+        }
+        rgb
+    }
+}
+
+/// ノイズ 1.0 のときの明るさの振れ幅
+const NOISE_SCALE: f32 = 0.25;
+
+/// 0..1 の擬似乱数（blur.wgsl の `noise_hash` と同じ式。GPU とは sin の精度の分だけずれる）
+pub fn noise_hash(pixel: [f32; 2], seed: f32) -> f32 {
+    // WGSL の fract と同じく負の値でも 0..1 に収める
+    let x = (pixel[0] * 12.9898 + pixel[1] * 78.233 + seed).sin() * 43758.547;
+    x - x.floor()
+}
+
+/// sRGB の 8 ビット値を線形値（0..1）にする
+pub fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,45 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32(i32(in_vertex_index == 1u || in_vertex_index == 2u || in_vertex_index == 5u)) * 2.0 - 1.0;
+    let y = f32(i32(in_vertex_index == 2u || in_vertex_index == 3u || in_vertex_index == 5u)) * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    out.tex_coords = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
+
+@group(0) @binding(0) var t_source: texture_2d<f32>;
+@group(0) @binding(1) var s_source: sampler;
+
+const MAX_KERNEL_TAPS: u32 = 32u; // kernel.rs の MAX_KERNEL_TAPS と揃えること
+
+struct BlurPass {
+    direction: vec2<f32>, // 1 タップあたりの移動量 (UV)。水平なら (step / width, 0)
+    tap_count: u32, // 中心を除く片側のタップ数
+    weights: array<vec4<f32>, 9>, // 中心から順に 4 つずつ詰めた重み ((MAX_KERNEL_TAPS + 4) / 4)
+};
+@group(1) @binding(0) var<uniform> blur: BlurPass;
+
+fn kernel_weight(i: u32) -> f32 {
+    return blur.weights[i / 4u][i % 4u];
+}
+
+// 一方向のガウスぼかし。水平・垂直の 2 パスで 2 次元のぼかしになる
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = textureSample(t_source, s_source, in.tex_coords) * kernel_weight(0u);
+    for (var i = 1u; i <= min(blur.tap_count, MAX_KERNEL_TAPS); i = i + 1u) {
+        let offset = blur.direction * f32(i);
+        let w = kernel_weight(i);
+        color += textureSample(t_source, s_source, in.tex_coords + offset) * w;
+        color += textureSample(t_source, s_source, in.tex_coords - offset) * w;
+    }
+    return color;
+}
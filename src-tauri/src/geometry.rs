@@ -0,0 +1,404 @@
+//! 座標系ごとの矩形と、その間の変換
+//!
+//! - `PhysicalRect`: 物理ピクセルのスクリーン座標（OS の API が返す座標）
+//! - `LogicalRect`: モニターのスケールで割った論理ピクセル（WebView の CSS ピクセル）
+//! - `NormalizedRect`: モニター左上を 0、右下を 1 とする正規化座標（シェーダーに渡す座標）
+//!
+//! 変換はすべて `MonitorGeometry`（モニターの位置・サイズ・スケール）を介して行う。
+
+use serde::Serialize;
+
+/// 96 DPI を 1.0 とするスケールの基準
+#[cfg(any(windows, test))]
+pub const BASE_DPI: u32 = 96;
+
+/// Windows 10 以降の不可視のリサイズ境界の幅（96 DPI 時、物理ピクセル）
+///
+/// DWM の拡張フレーム境界が取れないときに、ウィンドウ矩形から差し引くのに使う。
+#[cfg(any(windows, test))]
+pub const INVISIBLE_BORDER: i32 = 7;
+
+/// 可視フレームから穴を広げる幅。境界線の取りこぼしを防ぐ
+pub const HOLE_MARGIN: i32 = 1;
+
+/// 物理ピクセルのスクリーン座標の矩形（`right` / `bottom` は含まない）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PhysicalRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl PhysicalRect {
+    pub fn from_origin_size(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            left: x,
+            top: y,
+            right: x + width as i32,
+            bottom: y + height as i32,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    /// 幅か高さが 0 以下
+    pub fn is_empty(&self) -> bool {
+        self.width() <= 0 || self.height() <= 0
+    }
+
+    /// 上下左右に `px` ピクセルずつ広げた矩形を返す
+    pub fn inflate(&self, px: i32) -> PhysicalRect {
+        PhysicalRect {
+            left: self.left - px,
+            top: self.top - px,
+            right: self.right + px,
+            bottom: self.bottom + px,
+        }
+    }
+
+    /// `(dx, dy)` だけずらした矩形を返す
+    pub fn translate(&self, dx: i32, dy: i32) -> PhysicalRect {
+        PhysicalRect {
+            left: self.left + dx,
+            top: self.top + dy,
+            right: self.right + dx,
+            bottom: self.bottom + dy,
+        }
+    }
+
+    /// 重なっている部分。重ならなければ `None`
+    pub fn intersection(&self, other: &PhysicalRect) -> Option<PhysicalRect> {
+        let rect = PhysicalRect {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        };
+        (!rect.is_empty()).then_some(rect)
+    }
+}
+
+/// 論理ピクセルの矩形（モニター左上からの相対座標）
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct LogicalRect {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+#[cfg(test)]
+impl LogicalRect {
+    pub fn width(&self) -> f64 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f64 {
+        self.bottom - self.top
+    }
+}
+
+/// モニター相対の正規化座標の矩形（モニター内なら各値は 0..=1）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct NormalizedRect {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl NormalizedRect {
+    /// シェーダーの `vec4<f32>` に詰める形（left, top, right, bottom）
+    pub fn to_array(self) -> [f32; 4] {
+        [self.left, self.top, self.right, self.bottom]
+    }
+}
+
+/// 座標変換の基準になるモニター 1 枚分の位置・サイズ・スケール
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonitorGeometry {
+    /// モニター全体（物理スクリーン座標）
+    pub rect: PhysicalRect,
+    /// 論理ピクセル 1 つあたりの物理ピクセル数（125% なら 1.25）
+    pub scale_factor: f64,
+}
+
+impl MonitorGeometry {
+    pub fn new(rect: PhysicalRect, scale_factor: f64) -> Self {
+        Self { rect, scale_factor }
+    }
+
+    /// DPI からスケールを求める（`GetDpiForMonitor` などが返す値）
+    #[cfg(test)]
+    pub fn from_dpi(rect: PhysicalRect, dpi: u32) -> Self {
+        Self::new(rect, f64::from(dpi) / f64::from(BASE_DPI))
+    }
+
+    /// スクリーン座標をモニター左上からの相対座標にする
+    pub fn to_local(self, rect: &PhysicalRect) -> PhysicalRect {
+        rect.translate(-self.rect.left, -self.rect.top)
+    }
+
+    /// `rect` をモニターの範囲に切り詰める。重ならなければ `None`
+    pub fn clip(&self, rect: &PhysicalRect) -> Option<PhysicalRect> {
+        rect.intersection(&self.rect)
+    }
+
+    #[cfg(test)]
+    pub fn to_logical(self, rect: &PhysicalRect) -> LogicalRect {
+        let local = self.to_local(rect);
+        let scale = self.scale_factor.max(f64::EPSILON);
+        LogicalRect {
+            left: f64::from(local.left) / scale,
+            top: f64::from(local.top) / scale,
+            right: f64::from(local.right) / scale,
+            bottom: f64::from(local.bottom) / scale,
+        }
+    }
+
+    /// 論理座標を物理スクリーン座標に戻す（端数は四捨五入）
+    #[cfg(test)]
+    pub fn to_physical(self, rect: &LogicalRect) -> PhysicalRect {
+        let physical = |v: f64| (v * self.scale_factor).round() as i32;
+        PhysicalRect {
+            left: physical(rect.left),
+            top: physical(rect.top),
+            right: physical(rect.right),
+            bottom: physical(rect.bottom),
+        }
+        .translate(self.rect.left, self.rect.top)
+    }
+
+    pub fn normalize(&self, rect: &PhysicalRect) -> NormalizedRect {
+        let local = self.to_local(rect);
+        let width = self.rect.width().max(1) as f32;
+        let height = self.rect.height().max(1) as f32;
+        NormalizedRect {
+            left: local.left as f32 / width,
+            top: local.top as f32 / height,
+            right: local.right as f32 / width,
+            bottom: local.bottom as f32 / height,
+        }
+    }
+
+    /// 正規化座標を物理スクリーン座標に戻す（端数は四捨五入）
+    #[cfg(test)]
+    pub fn denormalize(&self, rect: &NormalizedRect) -> PhysicalRect {
+        let width = self.rect.width() as f32;
+        let height = self.rect.height() as f32;
+        PhysicalRect {
+            left: (rect.left * width).round() as i32,
+            top: (rect.top * height).round() as i32,
+            right: (rect.right * width).round() as i32,
+            bottom: (rect.bottom * height).round() as i32,
+        }
+        .translate(self.rect.left, self.rect.top)
+    }
+}
+
+/// ウィンドウの見た目どおりの枠（物理スクリーン座標）を求める
+///
+/// Windows のウィンドウ矩形は左右と下に不可視のリサイズ境界を含む。DWM の拡張フレーム境界が
+/// 取れればそれを使い、取れなければ境界の幅（`INVISIBLE_BORDER` を DPI で拡大したもの）を差し引く。
+#[cfg(any(windows, test))]
+pub fn visible_frame(
+    window_rect: PhysicalRect,
+    extended_frame: Option<PhysicalRect>,
+    dpi: u32,
+) -> PhysicalRect {
+    if let Some(frame) = extended_frame.filter(|f| !f.is_empty()) {
+        return frame;
+    }
+    let border = INVISIBLE_BORDER * dpi.max(1) as i32 / BASE_DPI as i32;
+    PhysicalRect {
+        left: window_rect.left + border,
+        top: window_rect.top,
+        right: window_rect.right - border,
+        bottom: window_rect.bottom - border,
+    }
+}
+
+/// 可視フレームから穴の矩形を求める（境界線の取りこぼしを防ぐため `HOLE_MARGIN` 広げる）
+pub fn hole_rect(frame: PhysicalRect) -> PhysicalRect {
+    frame.inflate(HOLE_MARGIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// スクリーン座標の矩形（マルチモニターの負の座標を含む。空の矩形もある）
+    fn physical_rect() -> impl Strategy<Value = PhysicalRect> {
+        (-8000i32..8000, -8000i32..8000, 0u32..4000, 0u32..4000)
+            .prop_map(|(x, y, width, height)| PhysicalRect::from_origin_size(x, y, width, height))
+    }
+
+    /// 640x480 以上のモニターと 75%〜500% のスケール
+    fn monitor() -> impl Strategy<Value = MonitorGeometry> {
+        (
+            -8000i32..8000,
+            -8000i32..8000,
+            640u32..7680,
+            480u32..4320,
+            72u32..=480,
+        )
+            .prop_map(|(x, y, width, height, dpi)| {
+                MonitorGeometry::from_dpi(PhysicalRect::from_origin_size(x, y, width, height), dpi)
+            })
+    }
+
+    /// モニターの周り（モニター 1 枚分はみ出すまで）の矩形
+    fn rect_near(monitor: &MonitorGeometry, fractions: [f64; 4]) -> PhysicalRect {
+        let (width, height) = (monitor.rect.width() as f64, monitor.rect.height() as f64);
+        let x = |f: f64| monitor.rect.left + (f * width) as i32;
+        let y = |f: f64| monitor.rect.top + (f * height) as i32;
+        let (left, right) = (
+            x(fractions[0].min(fractions[2])),
+            x(fractions[0].max(fractions[2])),
+        );
+        let (top, bottom) = (
+            y(fractions[1].min(fractions[3])),
+            y(fractions[1].max(fractions[3])),
+        );
+        PhysicalRect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn contains(outer: &PhysicalRect, inner: &PhysicalRect) -> bool {
+        outer.left <= inner.left
+            && outer.top <= inner.top
+            && inner.right <= outer.right
+            && inner.bottom <= outer.bottom
+    }
+
+    #[test]
+    fn logical_uses_monitor_scale() {
+        let monitor =
+            MonitorGeometry::from_dpi(PhysicalRect::from_origin_size(-1920, 0, 1920, 1080), 144);
+        let rect = PhysicalRect::from_origin_size(-1920 + 300, 150, 600, 300);
+        let logical = monitor.to_logical(&rect);
+        assert_eq!(
+            logical,
+            LogicalRect {
+                left: 200.0,
+                top: 100.0,
+                right: 600.0,
+                bottom: 300.0
+            }
+        );
+    }
+
+    #[test]
+    fn visible_frame_prefers_extended_frame() {
+        let window = PhysicalRect::from_origin_size(100, 100, 814, 607);
+        let frame = PhysicalRect::from_origin_size(107, 100, 800, 600);
+        assert_eq!(visible_frame(window, Some(frame), 96), frame);
+        // 取れなければ 96 DPI で 7 ピクセル、192 DPI で 14 ピクセル差し引く
+        assert_eq!(visible_frame(window, None, 96), frame);
+        assert_eq!(
+            visible_frame(window, Some(PhysicalRect::default()), 192),
+            PhysicalRect {
+                left: 114,
+                top: 100,
+                right: 900,
+                bottom: 693
+            }
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn logical_round_trip(monitor in monitor(), rect in physical_rect()) {
+            let logical = monitor.to_logical(&rect);
+            prop_assert_eq!(monitor.to_physical(&logical), rect);
+            let width = logical.width() * monitor.scale_factor;
+            prop_assert!((width - f64::from(rect.width())).abs() < 1e-6);
+            let height = logical.height() * monitor.scale_factor;
+            prop_assert!((height - f64::from(rect.height())).abs() < 1e-6);
+        }
+
+        #[test]
+        fn normalized_round_trip(
+            monitor in monitor(),
+            fractions in (-1.0f64..2.0, -1.0f64..2.0, -1.0f64..2.0, -1.0f64..2.0)
+        ) {
+            let (a, b, c, d) = fractions;
+            let rect = rect_near(&monitor, [a, b, c, d]);
+            prop_assert_eq!(monitor.denormalize(&monitor.normalize(&rect)), rect);
+        }
+
+        #[test]
+        fn clipped_rect_normalizes_into_unit_square(monitor in monitor(), rect in physical_rect()) {
+            if let Some(clipped) = monitor.clip(&rect) {
+                prop_assert!(contains(&monitor.rect, &clipped));
+                let n = monitor.normalize(&clipped);
+                for v in n.to_array() {
+                    prop_assert!((0.0..=1.0).contains(&v), "{:?}", n);
+                }
+                prop_assert!(n.left < n.right && n.top < n.bottom);
+            }
+        }
+
+        #[test]
+        fn intersection_is_symmetric_and_contained(a in physical_rect(), b in physical_rect()) {
+            let ab = a.intersection(&b);
+            prop_assert_eq!(ab, b.intersection(&a));
+            match ab {
+                Some(rect) => {
+                    prop_assert!(!rect.is_empty());
+                    prop_assert!(contains(&a, &rect) && contains(&b, &rect));
+                }
+                None => prop_assert!(
+                    a.is_empty()
+                        || b.is_empty()
+                        || a.right <= b.left
+                        || b.right <= a.left
+                        || a.bottom <= b.top
+                        || b.bottom <= a.top
+                ),
+            }
+        }
+
+        #[test]
+        fn hole_rect_is_clamped_to_monitor(monitor in monitor(), frame in physical_rect()) {
+            let hole = hole_rect(frame);
+            prop_assert_eq!(hole.width(), frame.width() + 2 * HOLE_MARGIN);
+            prop_assert_eq!(hole.height(), frame.height() + 2 * HOLE_MARGIN);
+            prop_assert!(contains(&hole, &frame));
+            if let Some(visible) = monitor.clip(&frame) {
+                // 広げた穴も切り詰めればモニターの内側に収まり、見えている部分は覆う
+                let clipped = monitor.clip(&hole);
+                prop_assert!(clipped.is_some());
+                let clipped = clipped.unwrap();
+                prop_assert!(contains(&monitor.rect, &clipped));
+                prop_assert!(contains(&clipped, &visible));
+            }
+        }
+
+        #[test]
+        fn visible_frame_stays_inside_window(
+            window in physical_rect(),
+            dpi in 72u32..=480
+        ) {
+            let border = INVISIBLE_BORDER * dpi as i32 / BASE_DPI as i32;
+            prop_assume!(window.width() > 2 * border && window.height() > border);
+            let frame = visible_frame(window, None, dpi);
+            prop_assert!(contains(&window, &frame));
+            prop_assert_eq!(frame.top, window.top);
+            prop_assert_eq!(window.right - frame.right, border);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Dual-Kawase ぼかしの段数計算と CPU 参照実装
+//!
+//! kawase.wgsl の `fs_down` / `fs_up` と同じサンプリングを行う。シェーダーを変更したらこちらも揃えること。
+
+/// 縮小の最大段数（これ以上は 1 段あたりのオフセットを広げて対応する）
+pub const MAX_KAWASE_ITERATIONS: usize = 6;
+
+/// オフセットの上限。これを超えるとサンプル間が開きすぎて格子状のアーティファクトが出る
+const MAX_KAWASE_OFFSET: f32 = 4.0;
+
+/// ぼかし半径から決めた縮小段数とサンプルのオフセット
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KawasePlan {
+    /// 縮小（と拡大）パスの段数。0 ならぼかさない
+    pub iterations: usize,
+    /// 半ピクセル単位のサンプルオフセット倍率
+    pub offset: f32,
+}
+
+impl KawasePlan {
+    /// 半径 `radius`（物理ピクセル）に近いぼかしになる段数を求める
+    ///
+    /// `n` 段・オフセット 1 でおよそ `2^(n+1)` ピクセルのぼかしになるので、
+    /// 段数を切り上げてからオフセットで半径を合わせる。
+    pub fn new(radius: f32) -> Self {
+        if radius < 1.0 {
+            return Self {
+                iterations: 0,
+                offset: 1.0,
+            };
+        }
+        let iterations =
+            ((radius / 2.0).log2().ceil().max(1.0) as usize).min(MAX_KAWASE_ITERATIONS);
+        let offset = radius / (1u32 << (iterations + 1)) as f32;
+        Self {
+            iterations,
+            offset: offset.clamp(0.5, MAX_KAWASE_OFFSET),
+        }
+    }
+}
+
+/// 線形 RGBA の浮動小数点画像
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 4]>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [f32; 4] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// ClampToEdge・バイリニアで UV 座標をサンプリングする（GPU のサンプラーと同じ規約）
+    pub fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+        // テクセル中心が (i + 0.5) / size
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let clamp_x = |x: f32| x.clamp(0.0, (self.width - 1) as f32) as u32;
+        let clamp_y = |y: f32| y.clamp(0.0, (self.height - 1) as f32) as u32;
+        let (xa, xb) = (clamp_x(x0), clamp_x(x0 + 1.0));
+        let (ya, yb) = (clamp_y(y0), clamp_y(y0 + 1.0));
+
+        let mut out = [0.0; 4];
+        for (c, value) in out.iter_mut().enumerate() {
+            let top = self.get(xa, ya)[c] * (1.0 - fx) + self.get(xb, ya)[c] * fx;
+            let bottom = self.get(xa, yb)[c] * (1.0 - fx) + self.get(xb, yb)[c] * fx;
+            *value = top * (1.0 - fy) + bottom * fy;
+        }
+        out
+    }
+
+    /// 各ピクセル中心の UV を `f` に渡して新しい画像を作る
+    pub(crate) fn render(width: u32, height: u32, f: impl Fn(f32, f32) -> [f32; 4]) -> Self {
+        let mut image = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let u = (x as f32 + 0.5) / width as f32;
+                let v = (y as f32 + 0.5) / height as f32;
+                image.pixels[(y * width + x) as usize] = f(u, v);
+            }
+        }
+        image
+    }
+}
+
+/// 重み付きサンプルの合計を `total` で割る
+fn weighted(source: &Image, taps: &[(f32, f32, f32)], u: f32, v: f32, total: f32) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+    for &(du, dv, w) in taps {
+        let s = source.sample(u + du, v + dv);
+        for (acc, value) in sum.iter_mut().zip(s) {
+            *acc += value * w;
+        }
+    }
+    sum.map(|c| c / total)
+}
+
+/// 半ピクセルオフセット（UV）。出力サイズの半ピクセルに倍率を掛けたもの
+pub fn half_pixel(width: u32, height: u32, offset: f32) -> [f32; 2] {
+    [
+        0.5 / width.max(1) as f32 * offset,
+        0.5 / height.max(1) as f32 * offset,
+    ]
+}
+
+/// 半分のサイズへの縮小パス（中心 4 + 斜め 4 点の 1 重みの計 8）
+pub fn downsample(source: &Image, offset: f32) -> Image {
+    let (width, height) = ((source.width / 2).max(1), (source.height / 2).max(1));
+    let [hx, hy] = half_pixel(width, height, offset);
+    let taps = [
+        (0.0, 0.0, 4.0),
+        (-hx, -hy, 1.0),
+        (hx, hy, 1.0),
+        (hx, -hy, 1.0),
+        (-hx, hy, 1.0),
+    ];
+    Image::render(width, height, |u, v| weighted(source, &taps, u, v, 8.0))
+}
+
+/// `width` x `height` への拡大パス（十字 4 点の重み 1 + 斜め 4 点の重み 2 の計 12）
+pub fn upsample(source: &Image, width: u32, height: u32, offset: f32) -> Image {
+    let [hx, hy] = half_pixel(width, height, offset);
+    let taps = [
+        (-hx * 2.0, 0.0, 1.0),
+        (-hx, hy, 2.0),
+        (0.0, hy * 2.0, 1.0),
+        (hx, hy, 2.0),
+        (hx * 2.0, 0.0, 1.0),
+        (hx, -hy, 2.0),
+        (0.0, -hy * 2.0, 1.0),
+        (-hx, -hy, 2.0),
+    ];
+    Image::render(width, height, |u, v| weighted(source, &taps, u, v, 12.0))
+}
+
+/// `plan` に従って縮小・拡大を繰り返した結果（入力と同じサイズ）
+pub fn blur(source: &Image, plan: KawasePlan) -> Image {
+    if plan.iterations == 0 {
+        return source.clone();
+    }
+
+    let mut chain = vec![source.clone()];
+    for _ in 0..plan.iterations {
+        let next = downsample(chain.last().unwrap(), plan.offset);
+        chain.push(next);
+    }
+
+    let mut current = chain.pop().unwrap();
+    while let Some(target) = chain.pop() {
+        current = upsample(&current, target.width, target.height, plan.offset);
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::synthetic::SyntheticFrameSource;
+    use crate::capture::{Frame, FrameSource};
+    use crate::effects::srgb_to_linear;
+    use crate::renderer::{testing, BlurMode, FrameRenderer, Params};
+
+    /// 縁から `margin` ピクセル離れた内側だけに模様のある画像（縁のクランプの影響を受けない）
+    fn framed_pattern(width: u32, height: u32, margin: u32) -> Image {
+        let mut image = Image::new(width, height);
+        for y in margin..height - margin {
+            for x in margin..width - margin {
+                let v = ((x * 7 + y * 13) % 17) as f32 / 16.0;
+                image.pixels[(y * width + x) as usize] = [v, 1.0 - v, 0.5, 1.0];
+            }
+        }
+        image
+    }
+
+    /// チャンネル `c` の合計に 1 ピクセルの面積（元の解像度基準）を掛けたもの
+    fn energy(image: &Image, c: usize, area: f32) -> f32 {
+        image.pixels.iter().map(|p| p[c]).sum::<f32>() * area
+    }
+
+    #[test]
+    fn plan_grows_with_radius() {
+        assert_eq!(KawasePlan::new(0.5).iterations, 0);
+        let mut last = 0;
+        for radius in [2.0, 8.0, 32.0, 128.0, 512.0] {
+            let plan = KawasePlan::new(radius);
+            assert!(plan.iterations >= last, "radius {radius}");
+            assert!((0.5..=MAX_KAWASE_OFFSET).contains(&plan.offset));
+            last = plan.iterations;
+        }
+        assert_eq!(KawasePlan::new(1.0e6).iterations, MAX_KAWASE_ITERATIONS);
+        // 2^(n+1) ちょうどならオフセット 1
+        assert_eq!(
+            KawasePlan::new(16.0),
+            KawasePlan {
+                iterations: 3,
+                offset: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn constant_image_stays_constant() {
+        let mut image = Image::new(40, 24);
+        image.pixels.fill([0.25, 0.5, 0.75, 1.0]);
+        let blurred = blur(&image, KawasePlan::new(20.0));
+        assert_eq!((blurred.width, blurred.height), (40, 24));
+        for pixel in &blurred.pixels {
+            for (c, expected) in [0.25, 0.5, 0.75, 1.0].into_iter().enumerate() {
+                assert!((pixel[c] - expected).abs() < 1e-5, "{pixel:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn downsample_and_upsample_preserve_energy() {
+        let image = framed_pattern(64, 48, 12);
+        let half = downsample(&image, 1.0);
+        assert_eq!((half.width, half.height), (32, 24));
+        let restored = upsample(&half, 64, 48, 1.0);
+        for c in 0..3 {
+            let total = energy(&image, c, 1.0);
+            assert!((energy(&half, c, 4.0) - total).abs() < total * 1e-4);
+            assert!((energy(&restored, c, 1.0) - total).abs() < total * 1e-4);
+        }
+    }
+
+    #[test]
+    fn blur_preserves_energy() {
+        let image = framed_pattern(128, 128, 40);
+        let blurred = blur(&image, KawasePlan::new(12.0));
+        for c in 0..3 {
+            let total = energy(&image, c, 1.0);
+            let after = energy(&blurred, c, 1.0);
+            assert!((after - total).abs() < total * 1e-3, "{after} vs {total}");
+        }
+        // ぼかしたのでピークは下がる
+        let peak = |image: &Image| image.pixels.iter().map(|p| p[0]).fold(0.0, f32::max);
+        assert!(peak(&blurred) < peak(&image));
+    }
+
+    #[test]
+    fn shader_matches_reference() {
+        let (width, height) = (96, 64);
+        let mut renderer = testing::headless(width, height);
+        renderer.set_blur_mode(BlurMode::DualKawase);
+        let mut source = SyntheticFrameSource::new(width, height);
+        let Some(frame) = source.next_frame(None).unwrap() else {
+            unreachable!("synthetic source always returns a frame");
+        };
+        renderer.upload_frame(&frame, (0, 0));
+
+        // キャプチャと同じ画像を線形 RGBA にして CPU でぼかす
+        let Frame::Cpu {
+            row_pitch, data, ..
+        } = frame
+        else {
+            unreachable!("synthetic frames live in CPU memory");
+        };
+        let mut image = Image::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let bgra = &data[(y * row_pitch + x * 4) as usize..][..4];
+                image.pixels[(y * width + x) as usize] = [
+                    srgb_to_linear(bgra[2]),
+                    srgb_to_linear(bgra[1]),
+                    srgb_to_linear(bgra[0]),
+                    1.0,
+                ];
+            }
+        }
+
+        for strength in [4.0, 12.0, 40.0] {
+            let params = Params::new(strength);
+            renderer.render(&params).unwrap();
+            let pixels = renderer.read_output().unwrap();
+            let plan = KawasePlan::new(crate::kernel::sigma_for_strength(strength, 1.0) * 3.0);
+            let expected = blur(&image, plan);
+
+            // 中間テクスチャが 8 ビット sRGB なので、パスごとの量子化の分だけずれる
+            let encode = |c: f32| {
+                let s = if c <= 0.0031308 {
+                    c * 12.92
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                };
+                s * 255.0
+            };
+            let mut total = 0.0;
+            for (i, (actual, expected)) in pixels.chunks_exact(4).zip(&expected.pixels).enumerate()
+            {
+                for c in 0..3 {
+                    let diff = (actual[c] as f32 - encode(expected[c])).abs();
+                    assert!(
+                        diff <= 6.0,
+                        "strength {} pixel ({}, {}) channel {}: gpu {} cpu {}",
+                        strength,
+                        i as u32 % width,
+                        i as u32 / width,
+                        c,
+                        actual[c],
+                        encode(expected[c])
+                    );
+                    total += diff;
+                }
+            }
+            let mean = total / (width * height * 3) as f32;
+            assert!(mean <= 1.5, "strength {strength}: mean difference {mean}");
+        }
+    }
+}
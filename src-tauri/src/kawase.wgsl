@@ -0,0 +1,57 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32(i32(in_vertex_index == 1u || in_vertex_index == 2u || in_vertex_index == 5u)) * 2.0 - 1.0;
+    let y = f32(i32(in_vertex_index == 2u || in_vertex_index == 3u || in_vertex_index == 5u)) * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    out.tex_coords = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
+
+@group(0) @binding(0) var t_source: texture_2d<f32>;
+@group(0) @binding(1) var s_source: sampler;
+
+struct KawasePass {
+    half_pixel: vec2<f32>, // 出力サイズの半ピクセル (UV) にオフセット倍率を掛けたもの
+};
+@group(1) @binding(0) var<uniform> pass_params: KawasePass;
+
+fn sample_at(uv: vec2<f32>) -> vec4<f32> {
+    return textureSample(t_source, s_source, uv);
+}
+
+// 半分のサイズへの縮小。kawase.rs の downsample と揃えること
+@fragment
+fn fs_down(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.tex_coords;
+    let hp = pass_params.half_pixel;
+    var sum = sample_at(uv) * 4.0;
+    sum += sample_at(uv - hp);
+    sum += sample_at(uv + hp);
+    sum += sample_at(uv + vec2<f32>(hp.x, -hp.y));
+    sum += sample_at(uv - vec2<f32>(hp.x, -hp.y));
+    return sum / 8.0;
+}
+
+// 倍のサイズへの拡大。kawase.rs の upsample と揃えること
+@fragment
+fn fs_up(in: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = in.tex_coords;
+    let hp = pass_params.half_pixel;
+    var sum = sample_at(uv + vec2<f32>(-hp.x * 2.0, 0.0));
+    sum += sample_at(uv + vec2<f32>(-hp.x, hp.y)) * 2.0;
+    sum += sample_at(uv + vec2<f32>(0.0, hp.y * 2.0));
+    sum += sample_at(uv + vec2<f32>(hp.x, hp.y)) * 2.0;
+    sum += sample_at(uv + vec2<f32>(hp.x * 2.0, 0.0));
+    sum += sample_at(uv + vec2<f32>(hp.x, -hp.y)) * 2.0;
+    sum += sample_at(uv + vec2<f32>(0.0, -hp.y * 2.0));
+    sum += sample_at(uv + vec2<f32>(-hp.x, -hp.y)) * 2.0;
+    return sum / 12.0;
+}
@@ -0,0 +1,162 @@
+//! ガウスぼかしのカーネル計算
+
+/// 片側のタップ数の上限（gaussian.wgsl の重み配列の長さと揃えること）
+pub const MAX_KERNEL_TAPS: usize = 32;
+
+/// タップ間隔の上限（テクセル）
+///
+/// 1 を超えるとバイリニアの山がタップごとに並んだ形になり、細い線や点が縞になって残る。
+pub const MAX_TAP_STEP: f32 = 1.0;
+
+/// ぼかす前に 1/2 へ縮小する回数の上限（`MAX_KAWASE_ITERATIONS` 以下にすること）
+pub const MAX_DOWNSAMPLE_LEVELS: usize = 5;
+
+/// 分離可能ガウスカーネルの片側
+///
+/// `weights[0]` が中心、`weights[i]` が中心から `i * step` テクセル離れた位置の重み。
+/// 両側を合わせた重みの合計は 1 になる。
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaussianKernel {
+    pub step: f32,
+    pub weights: Vec<f32>,
+}
+
+impl GaussianKernel {
+    /// 標準偏差 `sigma`（物理ピクセル）のカーネルを作る
+    ///
+    /// 半径は 3σ まで取る。`MAX_KERNEL_TAPS` に収まらない場合はタップ間隔を広げ、
+    /// 間はバイリニア補間に任せる。間隔を `MAX_TAP_STEP` 以下に保つには `GaussianPlan` を使う。
+    pub fn new(sigma: f32) -> Self {
+        if sigma <= 0.0 {
+            return Self {
+                step: 1.0,
+                weights: vec![1.0],
+            };
+        }
+
+        let radius = (sigma * 3.0).ceil();
+        let taps = (radius as usize).clamp(1, MAX_KERNEL_TAPS);
+        let step = radius / taps as f32;
+
+        let mut weights: Vec<f32> = (0..=taps)
+            .map(|i| {
+                let x = i as f32 * step;
+                (-(x * x) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        let sum = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        for w in &mut weights {
+            *w /= sum;
+        }
+
+        Self { step, weights }
+    }
+
+    /// 中心を除く片側のタップ数
+    pub fn taps(&self) -> usize {
+        self.weights.len() - 1
+    }
+}
+
+/// 縮小してからぼかすガウスぼかしの手順
+///
+/// 強いぼかしをそのままの解像度でかけるとタップ間隔が広がりすぎるので、
+/// 間隔が `MAX_TAP_STEP` に収まるまで 1/2 の縮小を重ね、縮小後の解像度でカーネルを作る。
+/// 縮小（2x2 の平均）と最後の拡大（バイリニア）で増える分散は σ² に比べて小さいので補正しない。
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaussianPlan {
+    /// ぼかす前に 1/2 へ縮小する回数
+    pub levels: usize,
+    /// 縮小後の解像度でのカーネル
+    pub kernel: GaussianKernel,
+}
+
+impl GaussianPlan {
+    /// 標準偏差 `sigma`（元の解像度の物理ピクセル）のぼかしの手順を作る
+    pub fn new(sigma: f32) -> Self {
+        let reach = MAX_KERNEL_TAPS as f32 * MAX_TAP_STEP;
+        let mut levels = 0;
+        while levels < MAX_DOWNSAMPLE_LEVELS && (sigma * 3.0).ceil() / (1 << levels) as f32 > reach
+        {
+            levels += 1;
+        }
+        Self {
+            levels,
+            kernel: GaussianKernel::new(sigma / (1 << levels) as f32),
+        }
+    }
+}
+
+/// ぼかしの強さ（論理ピクセルでのぼかし半径）を物理ピクセルの標準偏差に変換する
+///
+/// 論理ピクセル基準なので、同じ強さなら DPI の異なるモニターでも見た目が揃う。
+pub fn sigma_for_strength(blur_strength: f32, scale_factor: f64) -> f32 {
+    blur_strength.max(0.0) / 3.0 * scale_factor as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weights_are_normalized() {
+        for sigma in [0.5, 3.0, 10.0, 40.0, 200.0] {
+            let kernel = GaussianKernel::new(sigma);
+            let sum = kernel.weights[0] + 2.0 * kernel.weights[1..].iter().sum::<f32>();
+            assert!((sum - 1.0).abs() < 1e-5, "sigma {sigma}: sum {sum}");
+            assert!(kernel.taps() <= MAX_KERNEL_TAPS);
+        }
+    }
+
+    #[test]
+    fn zero_sigma_is_identity() {
+        let kernel = GaussianKernel::new(0.0);
+        assert_eq!(kernel.weights, vec![1.0]);
+        assert_eq!(GaussianPlan::new(0.0).levels, 0);
+    }
+
+    #[test]
+    fn small_sigma_samples_every_texel() {
+        let plan = GaussianPlan::new(5.0);
+        assert_eq!(plan.levels, 0);
+        assert_eq!(plan.kernel.step, 1.0);
+        assert_eq!(plan.kernel.taps(), 15);
+    }
+
+    #[test]
+    fn strong_blur_is_downsampled_to_keep_step_small() {
+        // 最大の強さを 4 倍の DPI で使ってもタップは 1 テクセルおき
+        let max_sigma = sigma_for_strength(crate::settings::MAX_BLUR_STRENGTH, 4.0);
+        let mut sigma = 1.0;
+        while sigma <= max_sigma {
+            let plan = GaussianPlan::new(sigma);
+            assert!(
+                plan.kernel.step <= MAX_TAP_STEP,
+                "sigma {sigma}: step {} after {} levels",
+                plan.kernel.step,
+                plan.levels
+            );
+            sigma *= 1.1;
+        }
+    }
+
+    #[test]
+    fn downsampled_plan_keeps_the_same_sigma() {
+        let plan = GaussianPlan::new(120.0);
+        assert_eq!(plan.levels, 4);
+        // 1/16 の解像度で σ = 7.5、半径 3σ を 1 テクセルおきに取る
+        assert_eq!(plan.kernel, GaussianKernel::new(7.5));
+        assert_eq!(plan.kernel.step, 1.0);
+        assert_eq!(plan.kernel.taps(), 23);
+    }
+
+    #[test]
+    fn strength_is_resolution_independent() {
+        // 同じ強さなら 4K（200%）の σ は 1080p（100%）のちょうど 2 倍
+        assert_eq!(
+            sigma_for_strength(30.0, 2.0),
+            2.0 * sigma_for_strength(30.0, 1.0)
+        );
+        assert_eq!(sigma_for_strength(-5.0, 1.0), 0.0);
+    }
+}
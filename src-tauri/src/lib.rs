@@ -1,116 +1,631 @@
+mod animation;
+mod backend;
+mod capture;
+mod commands;
+mod effects;
+mod geometry;
+mod kawase;
+mod kernel;
+mod logging;
+mod mask;
+mod overlay;
+mod renderer;
+mod settings;
+mod shortcuts;
 mod window_manager;
 
-use tauri::menu::{Menu, MenuItem};
+use animation::Tween;
+use backend::{BackdropStatus, OverlayBackend};
+use geometry::{MonitorGeometry, PhysicalRect};
+use logging::{LogLevel, Logging};
+use overlay::{OverlayConfig, OverlayRenderer};
+use settings::Settings;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::menu::{IsMenuItem, Menu, MenuItem, Submenu};
 use tauri::tray::TrayIconBuilder;
-use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::WindowsAndMessaging::{
-    GetWindowLongW, SetWindowLongW, SetWindowPos, ShowWindow, GWL_EXSTYLE, SWP_NOACTIVATE,
-    SWP_NOMOVE, SWP_NOSIZE, SW_SHOWMAXIMIZED, WS_EX_LAYERED, WS_EX_TRANSPARENT,
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindow,
+    WebviewWindowBuilder,
 };
+use window_manager::rules::{RuleSet, WindowRule};
 
-#[tauri::command]
-fn js_log(message: String) {
-    println!("JS LOG: {}", message);
+/// ぼかしを描くオーバーレイウィンドウのラベルの接頭辞（モニターごとに連番を付ける）
+const BLUR_WINDOW_LABEL: &str = "blur_overlay";
+
+/// フェード中に不透明度を更新する間隔
+const FADE_INTERVAL: Duration = Duration::from_millis(16);
+
+/// モニターの抜き差しを確認する間隔
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// トレイアイコンの ID（設定エラーをツールチップに出すため）
+const TRAY_ID: &str = "main";
+
+/// モニター 1 枚分のオーバーレイウィンドウ
+struct MonitorOverlay {
+    /// `monitor_key` で求めたモニターの識別子
+    monitor: String,
+    /// モニター全体の位置・サイズ（物理スクリーン座標）とスケール
+    geometry: MonitorGeometry,
+    window: WebviewWindow,
+    /// ぼかしの描画スレッド（DWM の背景効果もこのスレッドが付け外しする）
+    renderer: OverlayRenderer,
+}
+
+/// オーバーレイの描画方式とモニターごとのオーバーレイ
+struct OverlayState {
+    settings: Mutex<Settings>,
+    /// 実際に適用中の描画方式（設定の方式が使えなければフォールバックした先）
+    backend: Mutex<OverlayBackend>,
+    /// 使えない描画方式。OS のバージョンで使えない方式から始め、初期化に失敗した方式を足す
+    /// （後で使えた方式は外す）
+    unavailable_backends: Mutex<Vec<OverlayBackend>>,
+    overlays: Mutex<Vec<MonitorOverlay>>,
+    /// オーバーレイを表示中か（後から繋がったモニターもこれに合わせる）
+    visible: Mutex<bool>,
+    /// 表示・非表示のフェード（オーバーレイの不透明度）
+    fade: Mutex<Tween<f32>>,
+    /// 設定の再読み込みで差し替わるので、使うたびに取り出す
+    rules: Mutex<Arc<RuleSet>>,
+    /// ショートカットの一時停止で隠している間の再開予定時刻
+    paused_until: Mutex<Option<Instant>>,
+}
+
+/// モニターの識別子。名前が取れない環境では位置とサイズで代用する
+fn monitor_key(monitor: &tauri::Monitor) -> String {
+    match monitor.name() {
+        Some(name) => name.clone(),
+        None => format!(
+            "{}x{}+{}+{}",
+            monitor.size().width,
+            monitor.size().height,
+            monitor.position().x,
+            monitor.position().y
+        ),
+    }
+}
+
+fn monitor_geometry(monitor: &tauri::Monitor) -> MonitorGeometry {
+    let position = monitor.position();
+    let size = monitor.size();
+    MonitorGeometry::new(
+        PhysicalRect::from_origin_size(position.x, position.y, size.width, size.height),
+        monitor.scale_factor(),
+    )
+}
+
+/// `rect`（物理スクリーン座標）をちょうど覆うオーバーレイウィンドウを作る（非表示のまま）
+fn create_blur_window(app: &AppHandle, rect: PhysicalRect) -> tauri::Result<WebviewWindow> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let label = format!(
+        "{}_{}",
+        BLUR_WINDOW_LABEL,
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    let window = WebviewWindowBuilder::new(app, label, WebviewUrl::App("blur.html".into()))
+        .title("hamaguri-blur")
+        .decorations(false)
+        .transparent(true)
+        .skip_taskbar(true)
+        .visible(false)
+        .build()?;
+
+    // 論理座標だとモニターごとのスケールの違いでずれるので、物理座標で合わせる
+    window.set_position(PhysicalPosition::new(rect.left, rect.top))?;
+    window.set_size(PhysicalSize::new(rect.width() as u32, rect.height() as u32))?;
+    let _ = window.set_ignore_cursor_events(true);
+
+    overlay::set_click_through(&window);
+
+    Ok(window)
 }
 
-/// アクティブウィンドウの HWND を取得（ブラーウィンドウ自身を除外）
-fn get_active_window_hwnd(exclude_hwnd: Option<isize>) -> Option<HWND> {
-    let info = window_manager::get_active_window_info(exclude_hwnd);
-    info.map(|i| HWND(i.hwnd as *mut _))
+/// オーバーレイを表示する（フォーカスは奪わない）
+fn show_blur_window(window: &WebviewWindow) {
+    overlay::show_no_activate(window);
 }
 
-fn set_click_through(hwnd: HWND) {
-    unsafe {
-        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE);
-        SetWindowLongW(
-            hwnd,
-            GWL_EXSTYLE,
-            ex_style | (WS_EX_TRANSPARENT.0 | WS_EX_LAYERED.0) as i32,
+/// オーバーレイウィンドウのハンドル（穴やアクティブウィンドウの判定から除くため）
+fn overlay_handles(app: &AppHandle) -> Vec<isize> {
+    let state = app.state::<OverlayState>();
+    let overlays = state.overlays.lock().unwrap();
+    overlays
+        .iter()
+        .filter_map(|o| overlay::window_handle(&o.window))
+        .collect()
+}
+
+/// 接続中のモニターとオーバーレイを突き合わせ、増えたモニターには作り、無くなったモニターのものは閉じる
+///
+/// 設定で無効にしたモニターには作らない。ウィンドウの作成・破棄は状態のロックを外して行う
+/// （メインスレッドで待っているコマンドとの行き違いを避けるため）。
+fn sync_monitor_overlays(app: &AppHandle) {
+    // 監視スレッドと設定変更から同時に呼ばれても同じモニターに二重に作らない
+    static SYNC: Mutex<()> = Mutex::new(());
+    let _sync = SYNC.lock().unwrap();
+
+    let monitors = match app.available_monitors() {
+        Ok(monitors) => monitors,
+        Err(e) => {
+            tracing::warn!("Failed to enumerate monitors: {}", e);
+            return;
+        }
+    };
+    let state = app.state::<OverlayState>();
+    let settings = state.settings.lock().unwrap().clone();
+    let wanted: Vec<(String, MonitorGeometry)> = monitors
+        .iter()
+        .map(|m| (monitor_key(m), monitor_geometry(m)))
+        .filter(|(key, _)| {
+            settings
+                .monitors
+                .iter()
+                .find(|m| &m.name == key)
+                .is_none_or(|m| m.enabled)
+        })
+        .collect();
+
+    let removed: Vec<MonitorOverlay> = {
+        let mut overlays = state.overlays.lock().unwrap();
+        let (keep, removed) = std::mem::take(&mut *overlays)
+            .into_iter()
+            .partition(|o| wanted.contains(&(o.monitor.clone(), o.geometry)));
+        *overlays = keep;
+        removed
+    };
+    for overlay in removed {
+        tracing::info!("Overlay removed: {}", overlay.monitor);
+        // 描画スレッドの後始末が済んでから閉じる（メインスレッドから呼ばれても待たせない）
+        std::thread::spawn(move || {
+            overlay.renderer.stop();
+            let _ = overlay.window.destroy();
+        });
+    }
+
+    let existing: Vec<String> = state
+        .overlays
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|o| o.monitor.clone())
+        .collect();
+    let visible = *state.visible.lock().unwrap();
+    for (monitor, geometry) in wanted
+        .into_iter()
+        .filter(|(key, _)| !existing.contains(key))
+    {
+        let window = match create_blur_window(app, geometry.rect) {
+            Ok(window) => window,
+            Err(e) => {
+                tracing::warn!("Failed to create overlay for {}: {}", monitor, e);
+                continue;
+            }
+        };
+        let renderer = OverlayRenderer::start(
+            window.clone(),
+            OverlayConfig {
+                settings: settings.clone(),
+                rules: state.rules.lock().unwrap().clone(),
+            },
+            geometry,
+            on_backend_selected(app),
         );
+        if visible {
+            show_blur_window(&window);
+        }
+        tracing::info!("Overlay created: {} {:?}", monitor, geometry);
+        state.overlays.lock().unwrap().push(MonitorOverlay {
+            monitor,
+            geometry,
+            window,
+            renderer,
+        });
     }
+    update_applied_backend(app);
 }
 
-/// ブラーウィンドウをアクティブウィンドウの直下に配置
-fn set_blur_below_active(blur_hwnd: HWND, active_hwnd: HWND) {
-    unsafe {
-        // アクティブウィンドウの直下にブラーウィンドウを配置
-        let _ = SetWindowPos(
-            blur_hwnd,
-            Some(active_hwnd),
-            0,
-            0,
-            0,
-            0,
-            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+/// モニターの抜き差しを監視する（Tauri には通知が無いのでポーリングする）
+fn start_monitor_watch(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(MONITOR_POLL_INTERVAL);
+        sync_monitor_overlays(&app);
+    });
+}
+
+/// 描画スレッドが描画方式を選び終えたときに呼ぶもの（状態に記録してフロントエンドへ知らせる）
+fn on_backend_selected(app: &AppHandle) -> impl FnOnce() + Send + 'static {
+    let app = app.clone();
+    move || update_applied_backend(&app)
+}
+
+/// オーバーレイが実際に使っている描画方式を記録し、変わったらフロントエンドへ知らせる
+///
+/// モニターごとに選び直すが、同じ環境なら同じ方式に落ち着くので先頭のオーバーレイで代表させる。
+/// 先頭のオーバーレイがまだ選んでいる途中なら、選び終えたときにもう一度呼ばれる。
+fn update_applied_backend(app: &AppHandle) {
+    let state = app.state::<OverlayState>();
+    let (first, unavailable_changed) = {
+        let overlays = state.overlays.lock().unwrap();
+        let mut unavailable = state.unavailable_backends.lock().unwrap();
+        let before = unavailable.clone();
+        for overlay in overlays.iter() {
+            for kind in overlay.renderer.unavailable() {
+                if !unavailable.contains(&kind) {
+                    unavailable.push(kind);
+                }
+            }
+            if let Some(applied) = overlay.renderer.kind() {
+                unavailable.retain(|&kind| kind != applied);
+            }
+        }
+        let first = overlays.first().map(|o| o.renderer.kind());
+        (first, *unavailable != before)
+    };
+    let applied = match first {
+        Some(Some(kind)) => kind,
+        Some(None) => return,
+        None => state.settings.lock().unwrap().backend,
+    };
+    let previous = std::mem::replace(&mut *state.backend.lock().unwrap(), applied);
+    if previous != applied {
+        tracing::info!("Overlay backend: {:?}", applied);
+    }
+    // 方式が変わらなくても、使えない方式が増えたら設定画面の選択肢を更新させる
+    if previous != applied || unavailable_changed {
+        let _ = app.emit("overlay-backend-changed", applied);
+    }
+}
+
+/// 設定の描画方式・実際の描画方式・使えなかった方式
+fn backdrop_status(app: &AppHandle) -> BackdropStatus {
+    let state = app.state::<OverlayState>();
+    let requested = state.settings.lock().unwrap().backend;
+    let applied = *state.backend.lock().unwrap();
+    let unavailable = state.unavailable_backends.lock().unwrap().clone();
+    BackdropStatus {
+        requested,
+        applied,
+        unavailable,
+    }
+}
+
+/// 設定の描画方式でオーバーレイの描画スレッドを作り直す
+///
+/// 描画スレッドを待たずに戻る。実際に使えた方式は、選び終えた描画スレッドが
+/// `overlay-backend-changed` で知らせる。
+fn restart_overlay_renderers(app: &AppHandle) {
+    let state = app.state::<OverlayState>();
+    let config = || OverlayConfig {
+        settings: state.settings.lock().unwrap().clone(),
+        rules: state.rules.lock().unwrap().clone(),
+    };
+    for overlay in state.overlays.lock().unwrap().iter_mut() {
+        overlay.renderer.restart(
+            overlay.window.clone(),
+            config(),
+            overlay.geometry,
+            on_backend_selected(app),
         );
     }
 }
 
-fn create_blur_window(app: &AppHandle) -> tauri::Result<()> {
-    let window =
-        WebviewWindowBuilder::new(app, "blur_overlay", WebviewUrl::App("blur.html".into()))
-            .title("hamaguri-blur")
-            .decorations(false)
-            .transparent(true)
-            .visible(false)
-            .build()?;
+/// オーバーレイの描画方式を切り替えて設定に保存する
+///
+/// 選んだ方式がこの環境で使えないと分かっている場合は、保存はしたうえでエラーを返す。
+/// 試してみて使えなかった場合は、別の方式に落ちたことを `overlay-backend-changed` で知らせる。
+fn switch_overlay_backend(app: &AppHandle, backend: OverlayBackend) -> Result<(), String> {
+    let state = app.state::<OverlayState>();
+    let applied = *state.backend.lock().unwrap();
+    // 保存（ファイルの書き込み）の間は設定のロックを持たない
+    let saved = {
+        let mut settings = state.settings.lock().unwrap();
+        if settings.backend == backend && applied == backend {
+            return Ok(());
+        }
+        (settings.backend != backend).then(|| {
+            settings.backend = backend;
+            settings.clone()
+        })
+    };
+    if let Some(settings) = saved {
+        save_settings(app, &settings);
+    }
 
-    let _ = window.set_ignore_cursor_events(true);
+    restart_overlay_renderers(app);
+    if state
+        .unavailable_backends
+        .lock()
+        .unwrap()
+        .contains(&backend)
+    {
+        return Err(format!("{:?} is unavailable in this environment", backend));
+    }
+    Ok(())
+}
+
+/// 再読み込みした設定を反映する
+///
+/// 他のアプリと衝突するなどして登録できなかったショートカットがあればエラーを返すが、
+/// それ以外の設定は反映済みになる。
+fn apply_settings(app: &AppHandle, new: Settings) -> Result<(), String> {
+    let state = app.state::<OverlayState>();
+    let old = state.settings.lock().unwrap().clone();
+    let rules = Arc::new(new.rule_set().map_err(|e| e.to_string())?);
+
+    // 登録できなかったショートカットがあっても、残りの設定は反映する
+    let shortcut_failures = if new.shortcuts != old.shortcuts {
+        shortcuts::register_shortcuts(app, &new.shortcuts)
+    } else {
+        Vec::new()
+    };
 
-    if let Ok(hwnd) = window.hwnd() {
-        set_click_through(HWND(hwnd.0));
+    *state.settings.lock().unwrap() = new.clone();
+    *state.rules.lock().unwrap() = rules.clone();
+    if new.log_level != old.log_level {
+        app.state::<Logging>().set_level(new.log_level);
     }
 
-    // window-vibrancy で Acrylic ブラー効果を適用
-    // RGBA: (R, G, B, A) - A は透明度（0=完全透明, 255=不透明）
-    match window_vibrancy::apply_acrylic(&window, Some((18, 18, 18, 200))) {
-        Ok(_) => println!("Acrylic blur effect applied successfully"),
-        Err(e) => {
-            println!("Failed to apply acrylic: {:?}", e);
-            // フォールバック: 通常のブラーを試す
-            if let Err(e2) = window_vibrancy::apply_blur(&window, Some((18, 18, 18, 200))) {
-                println!("Failed to apply blur fallback: {:?}", e2);
-            } else {
-                println!("Blur fallback applied successfully");
-            }
+    if new.backend != old.backend {
+        restart_overlay_renderers(app);
+    } else {
+        for overlay in state.overlays.lock().unwrap().iter() {
+            overlay.renderer.update(OverlayConfig {
+                settings: new.clone(),
+                rules: rules.clone(),
+            });
         }
     }
+    if new.monitors != old.monitors {
+        // ウィンドウの作成はメインスレッドを塞がないよう別スレッドで行う
+        let app = app.clone();
+        std::thread::spawn(move || sync_monitor_overlays(&app));
+    }
 
+    let _ = app.emit("settings-changed", &new);
+    if !shortcut_failures.is_empty() {
+        return Err(format!(
+            "Failed to register shortcuts: {}",
+            shortcut_failures.join("; ")
+        ));
+    }
     Ok(())
 }
 
+/// 設定の読み込み・反映の失敗をトレイのツールチップと設定画面に知らせる
+fn report_settings_error(app: &AppHandle, message: &str) {
+    tracing::warn!("Settings error: {}", message);
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(format!("hamaguri-blur: {}", message)));
+    }
+    let _ = app.emit("settings-error", message);
+}
+
+/// 設定ファイルの変更を監視し、変わったら反映する（不正な内容なら直前の設定のまま）
+fn start_settings_watcher(app: &AppHandle) {
+    let Some(path) = settings_path(app) else {
+        return;
+    };
+    let app = app.clone();
+    settings::watch(path, move |result| {
+        let applied = result.map_err(|e| e.to_string()).and_then(|new| {
+            // アプリ自身が保存した内容を読み直しただけなら反映し直さない
+            if new == *app.state::<OverlayState>().settings.lock().unwrap() {
+                return Ok(false);
+            }
+            apply_settings(&app, new).map(|()| true)
+        });
+        match applied {
+            Ok(changed) => {
+                if changed {
+                    tracing::info!("Settings reloaded");
+                }
+                if let Some(tray) = app.tray_by_id(TRAY_ID) {
+                    let _ = tray.set_tooltip(Some("hamaguri-blur"));
+                }
+            }
+            Err(e) => report_settings_error(&app, &e),
+        }
+    });
+}
+
+fn settings_path(app: &AppHandle) -> Option<std::path::PathBuf> {
+    app.path()
+        .app_config_dir()
+        .ok()
+        .map(|dir| dir.join("settings.json"))
+}
+
+/// 設定ディレクトリの settings.json を読み込む
+///
+/// 無ければ既定値で作る。以前の rules.json があればそのルールを引き継ぐ。
+/// 読み込めなかった場合は既定値で動かし、ファイルは上書きしない。
+fn load_settings(app: &AppHandle) -> Settings {
+    let Some(path) = settings_path(app) else {
+        return Settings::default();
+    };
+    if path.exists() {
+        return match Settings::load(&path) {
+            Ok(settings) => {
+                tracing::info!("Loaded settings from {:?}", path);
+                settings
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load settings from {:?}: {}", path, e);
+                Settings::default()
+            }
+        };
+    }
+
+    let mut settings = Settings::default();
+    let legacy_rules = path.with_file_name("rules.json");
+    if legacy_rules.exists() {
+        match std::fs::read_to_string(&legacy_rules)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<Vec<WindowRule>>(&json).map_err(|e| e.to_string())
+            }) {
+            Ok(rules) => {
+                tracing::info!("Imported window rules from {:?}", legacy_rules);
+                settings.rules = rules;
+            }
+            Err(e) => tracing::warn!(
+                "Failed to import window rules from {:?}: {}",
+                legacy_rules,
+                e
+            ),
+        }
+    }
+    save_settings(app, &settings);
+    settings
+}
+
+fn save_settings(app: &AppHandle, settings: &Settings) {
+    let Some(path) = settings_path(app) else {
+        return;
+    };
+    if let Err(e) = settings.save(&path) {
+        tracing::warn!("Failed to save settings to {:?}: {}", path, e);
+    }
+}
+
+/// イベントフックが使えない環境で、z オーダーを見直す間隔
+const ZORDER_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// イベントフックが使える環境でも、取りこぼしに備えてこの間隔で見直す
+const ZORDER_FALLBACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// ウィンドウの変化に合わせてブラーウィンドウをアクティブウィンドウの直下へ移し、
+/// アクティブウィンドウが変わったら `active-window-changed` をフロントエンドへ通知する
 fn start_zorder_loop(app_handle: AppHandle) {
     std::thread::spawn(move || {
+        let _span = tracing::info_span!("zorder").entered();
+        let events = window_manager::subscribe_window_events();
+        let interval = if events.is_some() {
+            ZORDER_FALLBACK_INTERVAL
+        } else {
+            ZORDER_POLL_INTERVAL
+        };
+        let mut last_active: Option<(isize, PhysicalRect)> = None;
+
         loop {
-            std::thread::sleep(std::time::Duration::from_millis(50));
+            window_manager::wait_for_window_change(events.as_ref(), interval);
+
+            if !*app_handle.state::<OverlayState>().visible.lock().unwrap() {
+                continue;
+            }
 
-            if let Some(blur_window) = app_handle.get_webview_window("blur_overlay") {
-                if !blur_window.is_visible().unwrap_or(false) {
-                    continue;
+            // アクティブウィンドウを取得（オーバーレイ自身を除外）
+            let rules = app_handle
+                .state::<OverlayState>()
+                .rules
+                .lock()
+                .unwrap()
+                .clone();
+            let overlay_hwnds = overlay_handles(&app_handle);
+            let active = window_manager::get_active_window_info(&rules, &overlay_hwnds);
+            if let Some(info) = &active {
+                for &blur_hwnd in &overlay_hwnds {
+                    overlay::place_below(blur_hwnd, info.hwnd);
                 }
+            }
+
+            let current = active.as_ref().map(|info| (info.hwnd, info.rect));
+            if current != last_active {
+                last_active = current;
+                let _ = app_handle.emit("active-window-changed", &active);
+            }
+        }
+    });
+}
 
-                let blur_hwnd = match blur_window.hwnd() {
-                    Ok(h) => HWND(h.0),
-                    Err(_) => continue,
-                };
+/// オーバーレイ 1 枚の不透明度を変える
+///
+/// 自前で描く方式は描画時のアルファで、DWM の背景効果はレイヤードウィンドウのアルファで薄くする。
+fn set_overlay_opacity(overlay: &MonitorOverlay, opacity: f32) {
+    if !overlay
+        .renderer
+        .kind()
+        .is_some_and(|kind| kind.is_system_backdrop())
+    {
+        overlay.renderer.set_opacity(opacity);
+    } else {
+        overlay::set_window_alpha(&overlay.window, opacity);
+    }
+}
 
-                // アクティブウィンドウを取得（ブラーウィンドウ自身を除外）
-                if let Some(active_hwnd) = get_active_window_hwnd(Some(blur_hwnd.0 as isize)) {
-                    set_blur_below_active(blur_hwnd, active_hwnd);
+/// オーバーレイの不透明度を `visible` に向けて補間する
+///
+/// 途中でもう一度切り替えられたら、今の不透明度から折り返す（古いフェードのスレッドは抜ける）。
+/// 非表示にするときは、消え切ってからウィンドウを隠す。
+fn start_fade(app: &AppHandle, visible: bool) {
+    let state = app.state::<OverlayState>();
+    let transition = state.settings.lock().unwrap().fade;
+    let started = {
+        let mut fade = state.fade.lock().unwrap();
+        let target = if visible { 1.0 } else { 0.0 };
+        fade.retarget(target, Instant::now(), &transition);
+        fade.start
+    };
+
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        let state = app.state::<OverlayState>();
+        let fade = *state.fade.lock().unwrap();
+        if fade.start != started {
+            return;
+        }
+        let now = Instant::now();
+        let windows: Vec<WebviewWindow> = {
+            let overlays = state.overlays.lock().unwrap();
+            for overlay in overlays.iter() {
+                set_overlay_opacity(overlay, fade.value(now));
+            }
+            overlays.iter().map(|o| o.window.clone()).collect()
+        };
+        if fade.is_finished(now) {
+            // 隠す直前に切り替えられていたら、表示側のフェードに任せる
+            if !visible && state.fade.lock().unwrap().start == started {
+                for window in windows {
+                    let _ = window.hide();
                 }
             }
+            return;
         }
+        std::thread::sleep(FADE_INTERVAL);
     });
 }
 
-#[tauri::command]
-fn open_settings(app: AppHandle) {
-    if let Some(window) = app.get_webview_window("main") {
-        let _ = window.show();
-        let _ = window.set_focus();
+/// オーバーレイの表示・非表示を切り替え、切り替え後に表示中かどうかを返す
+///
+/// オーバーレイが 1 枚も無い（有効なモニターが無い）ときは `None`。
+fn toggle_overlay(app: &AppHandle) -> Option<bool> {
+    let state = app.state::<OverlayState>();
+    if state.overlays.lock().unwrap().is_empty() {
+        return None;
     }
+    // 手動で切り替えたら一時停止からの自動再開は取り消す
+    *state.paused_until.lock().unwrap() = None;
+    let visible = {
+        let mut visible = state.visible.lock().unwrap();
+        *visible = !*visible;
+        *visible
+    };
+    if visible {
+        // フェードアウトの途中なら今の不透明度のまま出して、そこから戻す
+        let opacity = state.fade.lock().unwrap().value(Instant::now());
+        for overlay in state.overlays.lock().unwrap().iter() {
+            let _ = overlay.window.set_ignore_cursor_events(true);
+            overlay::set_click_through(&overlay.window);
+            set_overlay_opacity(overlay, opacity);
+            show_blur_window(&overlay.window);
+        }
+    }
+    start_fade(app, visible);
+    tracing::info!("Blur overlay {}", if visible { "shown" } else { "hidden" });
+    let _ = app.emit("overlay-visibility-changed", visible);
+    Some(visible)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -120,40 +635,75 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .setup(|app| {
-            create_blur_window(app.handle())?;
+            let log_dir = app
+                .path()
+                .app_log_dir()
+                .unwrap_or_else(|_| std::env::temp_dir());
+            let logging = logging::init(&log_dir, LogLevel::default());
+            let settings = load_settings(app.handle());
+            logging.set_level(settings.log_level);
+            app.manage(logging);
+            let rules = Arc::new(settings.rule_set().unwrap_or_else(|e| {
+                tracing::warn!("Failed to compile window rules: {}", e);
+                RuleSet::default()
+            }));
+            let initial_backend = settings.backend;
+            let bindings = settings.shortcuts.clone();
+            app.manage(OverlayState {
+                settings: Mutex::new(settings),
+                backend: Mutex::new(initial_backend),
+                unavailable_backends: Mutex::new(overlay::unsupported_backdrops()),
+                overlays: Mutex::new(Vec::new()),
+                visible: Mutex::new(false),
+                fade: Mutex::new(Tween::settled(0.0, Instant::now())),
+                rules: Mutex::new(rules),
+                paused_until: Mutex::new(None),
+            });
+            sync_monitor_overlays(app.handle());
+            start_monitor_watch(app.handle().clone());
             start_zorder_loop(app.handle().clone());
 
-            let alt_b_shortcut = "Alt+B".parse::<Shortcut>().unwrap();
-            app.global_shortcut()
-                .on_shortcut(alt_b_shortcut, |app, _shortcut, event| {
-                    if event.state() == tauri_plugin_global_shortcut::ShortcutState::Pressed {
-                        if let Some(blur_window) = app.get_webview_window("blur_overlay") {
-                            let is_visible = blur_window.is_visible().unwrap_or(false);
-                            if is_visible {
-                                let _ = blur_window.hide();
-                                println!("Blur window hidden");
-                            } else {
-                                // フルスクリーン表示（Windows API で直接最大化）
-                                if let Ok(hwnd) = blur_window.hwnd() {
-                                    let hwnd = HWND(hwnd.0);
-                                    let _ = blur_window.set_ignore_cursor_events(true);
-                                    set_click_through(hwnd);
-                                    unsafe {
-                                        // SW_SHOWMAXIMIZED で最大化表示
-                                        let _ = ShowWindow(hwnd, SW_SHOWMAXIMIZED);
-                                    }
-                                }
-                                println!("Blur window shown");
-                            }
-                        }
-                    }
-                })?;
+            let shortcut_failures = shortcuts::register_shortcuts(app.handle(), &bindings);
 
             let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let show_i = MenuItem::with_id(app, "show", "Settings", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_i, &quit_i])?;
+            let acrylic_i =
+                MenuItem::with_id(app, "backend_acrylic", "System acrylic", true, None::<&str>)?;
+            let mica_i = MenuItem::with_id(app, "backend_mica", "System mica", true, None::<&str>)?;
+            let tabbed_i =
+                MenuItem::with_id(app, "backend_tabbed", "System mica alt", true, None::<&str>)?;
+            let blur_i = MenuItem::with_id(app, "backend_blur", "System blur", true, None::<&str>)?;
+            let gpu_i = MenuItem::with_id(app, "backend_gpu", "GPU blur", true, None::<&str>)?;
+            let cpu_i = MenuItem::with_id(app, "backend_cpu", "CPU blur", true, None::<&str>)?;
+            let level_items = LogLevel::ALL
+                .iter()
+                .map(|level| {
+                    let id = format!("log_level_{}", level.as_str());
+                    MenuItem::with_id(app, id, level.as_str(), true, None::<&str>)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let level_refs: Vec<&dyn IsMenuItem<_>> = level_items
+                .iter()
+                .map(|item| item as &dyn IsMenuItem<_>)
+                .collect();
+            let log_level_i = Submenu::with_items(app, "Log level", true, &level_refs)?;
+            let menu = Menu::with_items(
+                app,
+                &[
+                    &show_i,
+                    &acrylic_i,
+                    &mica_i,
+                    &tabbed_i,
+                    &blur_i,
+                    &gpu_i,
+                    &cpu_i,
+                    &log_level_i,
+                    &quit_i,
+                ],
+            )?;
 
-            let _tray = TrayIconBuilder::new()
+            let _tray = TrayIconBuilder::with_id(TRAY_ID)
+                .tooltip("hamaguri-blur")
                 .icon(app.default_window_icon().unwrap().clone())
                 .menu(&menu)
                 .on_menu_event(|app, event| match event.id.as_ref() {
@@ -166,13 +716,68 @@ pub fn run() {
                             let _ = window.set_focus();
                         }
                     }
-                    _ => {}
+                    "backend_acrylic" | "backend_mica" | "backend_tabbed" | "backend_blur"
+                    | "backend_gpu" | "backend_cpu" => {
+                        let backend = match event.id.as_ref() {
+                            "backend_acrylic" => OverlayBackend::SystemAcrylic,
+                            "backend_mica" => OverlayBackend::SystemMica,
+                            "backend_tabbed" => OverlayBackend::SystemTabbed,
+                            "backend_blur" => OverlayBackend::SystemBlur,
+                            "backend_gpu" => OverlayBackend::GpuBlur,
+                            _ => OverlayBackend::CpuBlur,
+                        };
+                        if let Err(e) = switch_overlay_backend(app, backend) {
+                            tracing::warn!("Failed to switch backend: {}", e);
+                        }
+                    }
+                    id => {
+                        let level = LogLevel::ALL
+                            .into_iter()
+                            .find(|level| id == format!("log_level_{}", level.as_str()));
+                        if let Some(level) = level {
+                            let mut settings =
+                                app.state::<OverlayState>().settings.lock().unwrap().clone();
+                            settings.log_level = level;
+                            if let Err(e) = commands::commit_settings(app, settings) {
+                                tracing::warn!("Failed to change log level: {:?}", e);
+                            }
+                        }
+                    }
                 })
                 .build(app)?;
 
+            // トレイを作ってから知らせる（ツールチップに出すため）
+            if !shortcut_failures.is_empty() {
+                report_settings_error(
+                    app.handle(),
+                    &format!(
+                        "Failed to register shortcuts: {}",
+                        shortcut_failures.join("; ")
+                    ),
+                );
+            }
+            start_settings_watcher(app.handle());
+
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![open_settings, js_log])
+        .invoke_handler(tauri::generate_handler![
+            commands::open_settings,
+            commands::js_log,
+            commands::set_log_level,
+            commands::get_overlay_backend,
+            commands::set_overlay_backend,
+            commands::get_backdrop_status,
+            commands::set_backdrop_tint,
+            commands::set_backdrop_theme,
+            commands::get_active_window,
+            commands::get_settings,
+            commands::update_settings,
+            commands::set_blur_strength,
+            commands::set_effect,
+            commands::toggle_overlay,
+            commands::list_windows,
+            commands::list_monitors
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
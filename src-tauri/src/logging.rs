@@ -0,0 +1,126 @@
+//! ログの出力先と出力レベル
+//!
+//! `tracing` のイベントを標準出力と、アプリのログディレクトリの日ごとに切り替わるファイルに書く。
+//! レベルは実行中に `set_level` で変えられる。フロントエンドのログも `frontend` ターゲットで
+//! 同じ流れに混ぜる（`source` に送り元のウィンドウを付ける）。
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+/// ログファイル名の接頭辞（`hamaguri-blur.log.2024-01-01` のようになる）
+const LOG_FILE_PREFIX: &str = "hamaguri-blur.log";
+
+/// 残しておくログファイルの数（日数）
+const MAX_LOG_FILES: usize = 7;
+
+/// 出力するログの最低レベル
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    /// フレームごとのキャプチャ・描画・ウィンドウ追跡まで出す
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// 初期化したロガー。破棄するとファイルへの書き出しが止まるので、アプリの終了まで持っておく
+pub struct Logging {
+    level: reload::Handle<LevelFilter, Registry>,
+    _file_guard: Option<WorkerGuard>,
+}
+
+impl Logging {
+    /// 出力レベルを変える（次のイベントから反映される）
+    pub fn set_level(&self, level: LogLevel) {
+        match self.level.modify(|filter| *filter = level.filter()) {
+            Ok(()) => tracing::info!("Log level: {}", level.as_str()),
+            Err(e) => tracing::warn!("Failed to change log level: {}", e),
+        }
+    }
+}
+
+/// グローバルなロガーを設定する。`log_dir` に書けなければ標準出力だけに出す
+pub fn init(log_dir: &Path, level: LogLevel) -> Logging {
+    let (filter, handle) = reload::Layer::new(level.filter());
+
+    let file = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir);
+    let (file_layer, file_guard) = match file {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = fmt::layer().with_ansi(false).with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        Err(e) => {
+            eprintln!("Failed to open log file in {:?}: {}", log_dir, e);
+            (None, None)
+        }
+    };
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(file_layer)
+        .try_init();
+    if let Err(e) = result {
+        eprintln!("Failed to initialize logging: {}", e);
+    }
+
+    Logging {
+        level: handle,
+        _file_guard: file_guard,
+    }
+}
+
+/// フロントエンドから送られたログを `frontend` ターゲットで出す
+pub fn frontend(source: &str, level: LogLevel, message: &str) {
+    match level {
+        LogLevel::Error => tracing::error!(target: "frontend", source, "{}", message),
+        LogLevel::Warn => tracing::warn!(target: "frontend", source, "{}", message),
+        LogLevel::Info => tracing::info!(target: "frontend", source, "{}", message),
+        LogLevel::Debug => tracing::debug!(target: "frontend", source, "{}", message),
+        LogLevel::Trace => tracing::trace!(target: "frontend", source, "{}", message),
+    }
+}
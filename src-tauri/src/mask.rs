@@ -0,0 +1,180 @@
+//! 穴マスクの CPU 参照実装
+//!
+//! blur.wgsl の `sd_rounded_rect` / `hole_mask` と同じ計算を行う。シェーダーを変更したらこちらも揃えること。
+
+/// 角丸矩形までの符号付き距離（ピクセル）。内側が負、外側が正
+///
+/// `rect` は [left, top, right, bottom]。`radius` は矩形の短辺の半分に切り詰める。
+pub fn sd_rounded_rect(p: [f32; 2], rect: [f32; 4], radius: f32) -> f32 {
+    let center = [(rect[0] + rect[2]) * 0.5, (rect[1] + rect[3]) * 0.5];
+    let half = [(rect[2] - rect[0]) * 0.5, (rect[3] - rect[1]) * 0.5];
+    let r = radius.clamp(0.0, half[0].min(half[1]).max(0.0));
+
+    let q = [
+        (p[0] - center[0]).abs() - half[0] + r,
+        (p[1] - center[1]).abs() - half[1] + r,
+    ];
+    let outside = q[0].max(0.0).hypot(q[1].max(0.0));
+    let inside = q[0].max(q[1]).min(0.0);
+    outside + inside - r
+}
+
+/// 境界からの距離をぼかしの強さ（0 = 鮮明、1 = 完全にぼかす）に変換する
+///
+/// `feather` が 0 以下なら境界で切り替わり、正なら境界の外側 `feather` ピクセルかけて滑らかに変化する。
+pub fn edge_mask(distance: f32, feather: f32) -> f32 {
+    if feather <= 0.0 {
+        return if distance > 0.0 { 1.0 } else { 0.0 };
+    }
+    let t = (distance / feather).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// 正規化座標 `uv` におけるマスク値（0 = 穴の中で鮮明、1 = 完全にぼかす）
+///
+/// `holes` は正規化座標の [left, top, right, bottom]、`radii` はそれぞれの角丸半径（ピクセル）、
+/// `resolution` は描画先の物理ピクセルサイズ。複数の穴が重なる場合は最も鮮明な値を採る。
+pub fn hole_mask(
+    uv: [f32; 2],
+    holes: &[[f32; 4]],
+    radii: &[f32],
+    resolution: [f32; 2],
+    feather: f32,
+) -> f32 {
+    let p = [uv[0] * resolution[0], uv[1] * resolution[1]];
+    holes
+        .iter()
+        .zip(radii)
+        .map(|(hole, &radius)| {
+            let rect = [
+                hole[0] * resolution[0],
+                hole[1] * resolution[1],
+                hole[2] * resolution[0],
+                hole[3] * resolution[1],
+            ];
+            edge_mask(sd_rounded_rect(p, rect, radius), feather)
+        })
+        .fold(1.0, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{testing, FrameRenderer, Params};
+
+    const RECT: [f32; 4] = [20.0, 10.0, 60.0, 50.0];
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn distance_to_square_corners() {
+        assert!(close(sd_rounded_rect([40.0, 30.0], RECT, 0.0), -20.0));
+        assert!(close(sd_rounded_rect([20.0, 30.0], RECT, 0.0), 0.0));
+        assert!(close(sd_rounded_rect([70.0, 30.0], RECT, 0.0), 10.0));
+        assert!(close(sd_rounded_rect([63.0, 54.0], RECT, 0.0), 5.0));
+    }
+
+    #[test]
+    fn rounded_corner_cuts_the_bounding_box() {
+        // 角の頂点は角丸の中心から r√2 離れているので、境界から r(√2 - 1) 外側
+        let r = 8.0;
+        let corner = sd_rounded_rect([60.0, 50.0], RECT, r);
+        assert!(close(corner, r * (2f32.sqrt() - 1.0)));
+        // 辺の中ほどは角丸の影響を受けない
+        assert!(close(sd_rounded_rect([60.0, 30.0], RECT, r), 0.0));
+        // 半径は短辺の半分で頭打ち（円になる）
+        assert!(close(sd_rounded_rect([60.0, 50.0], RECT, 100.0), {
+            20.0 * 2f32.sqrt() - 20.0
+        }));
+    }
+
+    #[test]
+    fn edge_without_feather_is_a_step() {
+        assert_eq!(edge_mask(-1.0, 0.0), 0.0);
+        assert_eq!(edge_mask(0.0, 0.0), 0.0);
+        assert_eq!(edge_mask(0.01, 0.0), 1.0);
+    }
+
+    #[test]
+    fn feather_band_is_smoothstep() {
+        assert_eq!(edge_mask(-3.0, 4.0), 0.0);
+        assert_eq!(edge_mask(0.0, 4.0), 0.0);
+        assert!(close(edge_mask(2.0, 4.0), 0.5));
+        assert!(close(edge_mask(1.0, 4.0), 0.15625));
+        assert_eq!(edge_mask(4.0, 4.0), 1.0);
+        assert_eq!(edge_mask(10.0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn mask_inside_outside_and_on_feather() {
+        let resolution = [100.0, 100.0];
+        let hole = [[0.2, 0.1, 0.6, 0.5]];
+        let mask =
+            |x: f32, y: f32| hole_mask([x / 100.0, y / 100.0], &hole, &[0.0], resolution, 4.0);
+        assert_eq!(mask(40.0, 30.0), 0.0);
+        assert_eq!(mask(90.0, 90.0), 1.0);
+        assert!(close(mask(62.0, 30.0), 0.5));
+        // 角の外側は両方向の距離で決まる
+        assert!(close(mask(62.0, 50.0), edge_mask(2.0, 4.0)));
+        assert!(mask(62.0, 52.0) > mask(62.0, 50.0));
+    }
+
+    #[test]
+    fn mask_respects_corner_radius() {
+        let resolution = [100.0, 100.0];
+        let hole = [[0.2, 0.1, 0.6, 0.5]];
+        let at_corner = |radius: f32| hole_mask([0.595, 0.495], &hole, &[radius], resolution, 0.0);
+        assert_eq!(at_corner(0.0), 0.0);
+        assert_eq!(at_corner(10.0), 1.0);
+    }
+
+    #[test]
+    fn overlapping_holes_take_the_clearest() {
+        let resolution = [100.0, 100.0];
+        let holes = [[0.0, 0.0, 0.5, 0.5], [0.4, 0.4, 1.0, 1.0]];
+        let mask = |x: f32, y: f32| hole_mask([x, y], &holes, &[0.0, 0.0], resolution, 10.0);
+        assert_eq!(mask(0.45, 0.45), 0.0);
+        assert_eq!(mask(0.7, 0.7), 0.0);
+        assert!(mask(0.55, 0.2) > 0.0 && mask(0.55, 0.2) < 1.0);
+        assert_eq!(hole_mask([0.5, 0.5], &[], &[], resolution, 10.0), 1.0);
+    }
+
+    /// blur.wgsl の `hole_mask` が出力のアルファ（マスク × 不透明度）に出るので、画素ごとに突き合わせる
+    #[test]
+    fn shader_mask_matches_reference() {
+        let (width, height) = (96, 64);
+        let mut renderer = testing::headless(width, height);
+        let mut params = Params::new(0.0);
+        params.resolution = [width as f32, height as f32];
+        params.hole_count = 3;
+        params.holes[0] = [0.1, 0.1, 0.5, 0.6];
+        params.holes[1] = [0.45, 0.3, 0.9, 0.9];
+        params.holes[2] = [0.7, 0.0, 1.0, 0.2];
+        params.hole_radii[0] = [12.0, 4.0, 0.0, 0.0];
+        params.feather = 6.0;
+        params.opacity = 0.8;
+        renderer.render(&params).unwrap();
+        let pixels = renderer.read_output().unwrap();
+
+        for y in 0..height {
+            for x in 0..width {
+                let uv = [
+                    (x as f32 + 0.5) / width as f32,
+                    (y as f32 + 0.5) / height as f32,
+                ];
+                let expected = (params.mask_at(uv) * params.opacity * 255.0).round();
+                let actual = pixels[((y * width + x) * 4 + 3) as usize] as f32;
+                assert!(
+                    (actual - expected).abs() <= 1.0,
+                    "alpha at ({}, {}): gpu {} cpu {}",
+                    x,
+                    y,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+#[cfg(not(windows))]
+mod generic;
+#[cfg(windows)]
+mod win32;
+
+#[cfg(not(windows))]
+use generic as platform;
+#[cfg(windows)]
+use win32 as platform;
+
+pub use platform::{
+    place_below, set_click_through, set_window_alpha, show_no_activate, unsupported_backdrops,
+};
+
+use crate::animation::{HoleAnimator, SystemClock};
+use crate::backend::{self, BlurBackend, NullBackend, OverlayBackend};
+use crate::effects::EffectChain;
+use crate::geometry::{MonitorGeometry, PhysicalRect};
+use crate::renderer::cpu::CpuRenderer;
+use crate::renderer::{Params, Renderer};
+use crate::settings::Settings;
+use crate::window_manager::{self, rules::RuleSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::WebviewWindow;
+use wgpu::rwh::{HasWindowHandle, RawWindowHandle};
+
+/// 描画間隔（約 60fps）
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// ウィンドウの変化が届かなくても、取りこぼしに備えて穴を数え直す間隔
+const HOLE_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 描画スレッドが使う設定とウィンドウ判定ルール
+pub struct OverlayConfig {
+    pub settings: Settings,
+    pub rules: Arc<RuleSet>,
+}
+
+/// 描画スレッドが選んだ描画方式
+#[derive(Debug, Clone, Default)]
+struct Selection {
+    /// 実際に使っている描画方式（フォールバックした結果）。選び終わるまでは `None`
+    kind: Option<OverlayBackend>,
+    /// 選ぶ途中で初期化に失敗した方式
+    unavailable: Vec<OverlayBackend>,
+}
+
+/// オーバーレイ 1 枚分の描画スレッド
+///
+/// 設定の描画方式から順に `BlurBackend` を試し、使えたものでスレッドが終わるまで描き続ける。
+/// 呼び出し元（メインスレッドのことが多い）は描画スレッドを待たない。描画スレッドはウィンドウの
+/// 問い合わせでメインスレッドを待つことがあり、互いに待つと行き詰まるため。
+pub struct OverlayRenderer {
+    selection: Arc<Mutex<Selection>>,
+    stop: Arc<AtomicBool>,
+    /// 描画全体の不透明度（`f32` のビット表現）。フェードで変える
+    opacity: Arc<AtomicU32>,
+    updates: Sender<OverlayConfig>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OverlayRenderer {
+    /// `window` への描画を別スレッドで開始する
+    ///
+    /// `surface` はウィンドウのクライアント領域と同じ位置・サイズ・スケール（メインスレッドで読んだもの）。
+    /// 描画方式が決まったら描画スレッドから `on_selected` を呼ぶ。
+    pub fn start(
+        window: WebviewWindow,
+        config: OverlayConfig,
+        surface: MonitorGeometry,
+        on_selected: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self::spawn(window, config, surface, on_selected, None)
+    }
+
+    fn spawn(
+        window: WebviewWindow,
+        config: OverlayConfig,
+        surface: MonitorGeometry,
+        on_selected: impl FnOnce() + Send + 'static,
+        previous: Option<JoinHandle<()>>,
+    ) -> Self {
+        let selection = Arc::new(Mutex::new(Selection::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let opacity = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let (updates, rx) = mpsc::channel();
+        let thread = {
+            let selection = selection.clone();
+            let stop = stop.clone();
+            let opacity = opacity.clone();
+            std::thread::spawn(move || {
+                let _span = tracing::info_span!("overlay", window = window.label()).entered();
+                // 前の方式の後始末（Acrylic を外すなど）が済んでから新しい方式を始める
+                if let Some(previous) = previous {
+                    let _ = previous.join();
+                }
+                let window = Arc::new(window);
+                let selected = move |kind, unavailable| {
+                    *selection.lock().unwrap() = Selection {
+                        kind: Some(kind),
+                        unavailable,
+                    };
+                    on_selected();
+                };
+                if let Err(e) = run(window, config, surface, &rx, &stop, &opacity, selected) {
+                    tracing::error!("Overlay renderer stopped: {}", e);
+                }
+            })
+        };
+        Self {
+            selection,
+            stop,
+            opacity,
+            updates,
+            thread: Some(thread),
+        }
+    }
+
+    /// 実際に使っている描画方式。まだ選んでいる途中なら `None`
+    pub fn kind(&self) -> Option<OverlayBackend> {
+        self.selection.lock().unwrap().kind
+    }
+
+    /// 選ぶ途中で初期化に失敗した方式
+    pub fn unavailable(&self) -> Vec<OverlayBackend> {
+        self.selection.lock().unwrap().unavailable.clone()
+    }
+
+    /// 設定を差し替える（次のフレームから反映される）
+    pub fn update(&self, config: OverlayConfig) {
+        let _ = self.updates.send(config);
+    }
+
+    /// 描画全体の不透明度（0..=1）を変える（次のフレームから反映される）
+    pub fn set_opacity(&self, opacity: f32) {
+        self.opacity
+            .store(opacity.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// 今のスレッドを止め、`config` の描画方式で始め直す
+    ///
+    /// 待たずに戻る。新しいスレッドが前のスレッドの終了を待ってから描画方式を選ぶ。
+    pub fn restart(
+        &mut self,
+        window: WebviewWindow,
+        config: OverlayConfig,
+        surface: MonitorGeometry,
+        on_selected: impl FnOnce() + Send + 'static,
+    ) {
+        self.stop.store(true, Ordering::Relaxed);
+        let previous = self.thread.take();
+        *self = Self::spawn(window, config, surface, on_selected, previous);
+    }
+
+    /// 描画スレッドを止めて終了を待つ
+    ///
+    /// 描画スレッドはメインスレッドを待つことがあるので、メインスレッドからは呼ばないこと。
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for OverlayRenderer {
+    /// 止めるよう伝えるだけで、終了は待たない
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// `WindowInfo::hwnd` と同じ形のウィンドウの識別子（Windows の HWND / X11 の Window ID）
+pub fn window_handle(window: &WebviewWindow) -> Option<isize> {
+    match window.window_handle().ok()?.as_raw() {
+        RawWindowHandle::Win32(handle) => Some(handle.hwnd.get()),
+        RawWindowHandle::Xlib(handle) => Some(handle.window as isize),
+        RawWindowHandle::Xcb(handle) => Some(handle.window.get() as isize),
+        _ => None,
+    }
+}
+
+/// ウィンドウのクライアント領域（物理スクリーン座標）
+fn surface_rect(window: &WebviewWindow) -> tauri::Result<PhysicalRect> {
+    let position = window.inner_position()?;
+    let size = window.inner_size()?;
+    Ok(PhysicalRect::from_origin_size(
+        position.x,
+        position.y,
+        size.width,
+        size.height,
+    ))
+}
+
+fn run(
+    window: Arc<WebviewWindow>,
+    config: OverlayConfig,
+    surface: MonitorGeometry,
+    updates: &Receiver<OverlayConfig>,
+    stop: &AtomicBool,
+    opacity: &AtomicU32,
+    selected: impl FnOnce(OverlayBackend, Vec<OverlayBackend>),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut backend, unavailable) = backend::select(config.settings.backend, |kind| {
+        create_backend(kind, &window, &config.settings, surface)
+    });
+    tracing::info!("Overlay backend: {:?}", backend.kind());
+    selected(backend.kind(), unavailable);
+
+    // オーバーレイ自身がキャプチャに映るとぼかしが自分自身を拾ってしまう
+    let capture = backend.needs_capture();
+    if capture {
+        platform::set_exclude_from_capture(&window, true)?;
+    }
+    let result = render_loop(&window, backend.as_mut(), config, updates, stop, opacity);
+    backend.shutdown();
+    if capture {
+        let _ = platform::set_exclude_from_capture(&window, false);
+    }
+    result
+}
+
+fn render_loop(
+    window: &Arc<WebviewWindow>,
+    backend: &mut dyn BlurBackend,
+    mut config: OverlayConfig,
+    updates: &Receiver<OverlayConfig>,
+    stop: &AtomicBool,
+    opacity: &AtomicU32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut capture = if backend.needs_capture() {
+        platform::capture_source(window)?
+    } else {
+        None
+    };
+    let exclude: Vec<isize> = window_handle(window).into_iter().collect();
+    backend.set_params(&config.settings);
+    let mut params = Params::new(config.settings.blur_strength);
+    // 穴の移動やぼかしの強さの変化は目標値に向かって毎フレーム補間する
+    let mut animator = HoleAnimator::new(
+        SystemClock,
+        config.settings.transition,
+        config.settings.blur_strength,
+    );
+    // 穴はウィンドウの変化が届いたときだけ数え直す（フックが無ければ毎フレーム）
+    let events = window_manager::subscribe_window_events();
+    let mut holes_updated_at: Option<Instant> = None;
+    let mut output: Option<MonitorGeometry> = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(FRAME_INTERVAL);
+
+        if let Some(latest) = updates.try_iter().last() {
+            config = latest;
+            backend.set_params(&config.settings);
+            animator.transition = config.settings.transition;
+            animator.set_strength(config.settings.blur_strength);
+            // ルールや穴の方式が変わったかもしれないので数え直す
+            holes_updated_at = None;
+        }
+
+        if !window.is_visible().unwrap_or(false) {
+            continue;
+        }
+
+        // ウィンドウの状態は一時的に読めないことがあるので、このフレームを飛ばすだけにする
+        let surface = match surface_rect(window)
+            .and_then(|rect| Ok(MonitorGeometry::new(rect, window.scale_factor()?)))
+        {
+            Ok(surface) => surface,
+            Err(e) => {
+                tracing::warn!("Failed to read overlay geometry: {}", e);
+                continue;
+            }
+        };
+        if output != Some(surface) {
+            backend.resize(
+                surface.rect.width() as u32,
+                surface.rect.height() as u32,
+                surface.scale_factor,
+            );
+            output = Some(surface);
+        }
+
+        let changed = match &events {
+            Some(rx) => rx.try_iter().count() > 0,
+            None => true,
+        };
+        let stale = holes_updated_at.is_none_or(|t| t.elapsed() >= HOLE_REFRESH_INTERVAL);
+        if changed || stale {
+            animator.set_holes(&window_manager::get_hole_windows(
+                &config.rules,
+                &exclude,
+                config.settings.hole_mode,
+            ));
+            holes_updated_at = Some(Instant::now());
+        }
+        params.set_holes(&animator.holes(), &surface);
+        params.blur_strength = animator.strength();
+        params.opacity = f32::from_bits(opacity.load(Ordering::Relaxed));
+        // モニターを跨ぐとスケールが変わるので毎フレーム換算する
+        params.effects = EffectChain::from_effects(&config.settings.effects, surface.scale_factor);
+
+        // 新しいフレームが無ければ前回のキャプチャのまま穴だけ更新する
+        let frame = match capture.as_mut() {
+            Some(capture) => {
+                let _span = tracing::trace_span!("capture").entered();
+                match capture.next_frame(backend.device()) {
+                    Ok(Some(frame)) => Some(frame),
+                    Ok(None) => {
+                        tracing::trace!("No new frame");
+                        None
+                    }
+                    Err(e) => {
+                        tracing::warn!("Capture failed: {}", e);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        // キャプチャはモニター全体なので、ウィンドウの位置から切り出す
+        let source_offset = match &frame {
+            Some(_) => {
+                let monitor_origin = match window.current_monitor() {
+                    Ok(monitor) => monitor
+                        .map(|m| *m.position())
+                        .unwrap_or(tauri::PhysicalPosition { x: 0, y: 0 }),
+                    Err(e) => {
+                        tracing::warn!("Failed to read overlay monitor: {}", e);
+                        continue;
+                    }
+                };
+                (
+                    (surface.rect.left - monitor_origin.x).max(0) as u32,
+                    (surface.rect.top - monitor_origin.y).max(0) as u32,
+                )
+            }
+            None => (0, 0),
+        };
+
+        let _span = tracing::trace_span!("render", holes = params.hole_count).entered();
+        let frame = frame.as_ref().map(|frame| (frame, source_offset));
+        if let Err(e) = backend.render_frame(frame, &params) {
+            tracing::warn!("Render failed: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// `kind` の描画方式を作る（初期化は `backend::select` が行う）
+fn create_backend(
+    kind: OverlayBackend,
+    window: &Arc<WebviewWindow>,
+    settings: &Settings,
+    surface: MonitorGeometry,
+) -> Result<Box<dyn BlurBackend>, Box<dyn std::error::Error>> {
+    let window = window.clone();
+    let (width, height) = (surface.rect.width() as u32, surface.rect.height() as u32);
+    Ok(match kind {
+        OverlayBackend::SystemAcrylic
+        | OverlayBackend::SystemMica
+        | OverlayBackend::SystemTabbed
+        | OverlayBackend::SystemBlur => platform::system_backdrop(kind, &window, settings)?,
+        OverlayBackend::GpuBlur => Box::new(tauri::async_runtime::block_on(Renderer::new(
+            window,
+            width,
+            height,
+            surface.scale_factor,
+        ))?),
+        OverlayBackend::CpuBlur => {
+            let mut renderer = CpuRenderer::new(width, height, surface.scale_factor);
+            renderer.present = Some(platform::layered_presenter(&window)?);
+            Box::new(renderer)
+        }
+        OverlayBackend::Null => Box::new(NullBackend),
+    })
+}
@@ -0,0 +1,55 @@
+//! Windows 以外でのオーバーレイウィンドウの操作
+//!
+//! 開発やテストで動かすための最低限の実装。画面キャプチャ・DWM の背景効果・レイヤード
+//! ウィンドウは無いので、GPU ブラーがキャプチャ無しで穴だけを描く。
+
+use crate::backend::{BlurBackend, OverlayBackend};
+use crate::capture::FrameSource;
+use crate::renderer::cpu::Presenter;
+use crate::settings::Settings;
+use tauri::WebviewWindow;
+
+/// `set_ignore_cursor_events` だけで足りる
+pub fn set_click_through(_window: &WebviewWindow) {}
+
+pub fn show_no_activate(window: &WebviewWindow) {
+    let _ = window.show();
+}
+
+/// 重なり順は変えない（ウィンドウマネージャに任せる）
+pub fn place_below(_overlay: isize, _active: isize) {}
+
+/// OS の合成でぼかす方式が無いので何もしない
+pub fn set_window_alpha(_window: &WebviewWindow, _opacity: f32) {}
+
+pub fn set_exclude_from_capture(
+    _window: &WebviewWindow,
+    _exclude: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Ok(())
+}
+
+/// 画面キャプチャは無い（`None` ならキャプチャ無しで描く）
+pub fn capture_source(
+    _window: &WebviewWindow,
+) -> Result<Option<Box<dyn FrameSource>>, Box<dyn std::error::Error>> {
+    tracing::warn!("Screen capture is not supported on this platform");
+    Ok(None)
+}
+
+pub fn layered_presenter(_window: &WebviewWindow) -> Result<Presenter, Box<dyn std::error::Error>> {
+    Err("layered windows are only supported on Windows".into())
+}
+
+/// DWM が無いので OS の合成でぼかす方式はどれも使えない
+pub fn unsupported_backdrops() -> Vec<OverlayBackend> {
+    OverlayBackend::SYSTEM_BACKDROPS.to_vec()
+}
+
+pub fn system_backdrop(
+    kind: OverlayBackend,
+    _window: &WebviewWindow,
+    _settings: &Settings,
+) -> Result<Box<dyn BlurBackend>, Box<dyn std::error::Error>> {
+    Err(format!("{:?} is only supported on Windows", kind).into())
+}
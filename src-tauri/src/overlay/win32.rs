@@ -0,0 +1,224 @@
+//! オーバーレイウィンドウの Win32 側の操作
+//!
+//! クリックの透過・z オーダー・レイヤードウィンドウのアルファ、キャプチャからの除外、
+//! CPU 描画結果の `UpdateLayeredWindow`、DWM の背景効果をここにまとめる。
+
+use crate::backend::dwm::{DwmBackdrop, Material};
+use crate::backend::{BlurBackend, OverlayBackend};
+use crate::capture::win32::{monitor_capture_item, CaptureSession};
+use crate::capture::FrameSource;
+use crate::renderer::cpu::Presenter;
+use crate::settings::Settings;
+use tauri::WebviewWindow;
+use windows::Win32::Foundation::{COLORREF, HWND, POINT, SIZE};
+use windows::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, GetDC, MonitorFromWindow,
+    ReleaseDC, SelectObject, AC_SRC_ALPHA, AC_SRC_OVER, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+    BLENDFUNCTION, DIB_RGB_COLORS, MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetWindowLongW, SetLayeredWindowAttributes, SetWindowDisplayAffinity, SetWindowLongW,
+    SetWindowPos, ShowWindow, UpdateLayeredWindow, GWL_EXSTYLE, LWA_ALPHA, SWP_NOACTIVATE,
+    SWP_NOMOVE, SWP_NOSIZE, SW_SHOWNOACTIVATE, ULW_ALPHA, WDA_EXCLUDEFROMCAPTURE, WDA_NONE,
+    WS_EX_LAYERED, WS_EX_TRANSPARENT,
+};
+
+fn hwnd(window: &WebviewWindow) -> Option<HWND> {
+    window.hwnd().ok().map(|hwnd| HWND(hwnd.0))
+}
+
+/// クリックを下のウィンドウへ素通りさせる
+pub fn set_click_through(window: &WebviewWindow) {
+    let Some(hwnd) = hwnd(window) else {
+        return;
+    };
+    unsafe {
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE);
+        SetWindowLongW(
+            hwnd,
+            GWL_EXSTYLE,
+            ex_style | (WS_EX_TRANSPARENT.0 | WS_EX_LAYERED.0) as i32,
+        );
+    }
+}
+
+/// フォーカスを奪わずに表示する
+pub fn show_no_activate(window: &WebviewWindow) {
+    if let Some(hwnd) = hwnd(window) {
+        unsafe {
+            let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+        }
+    }
+}
+
+/// オーバーレイ `overlay` をウィンドウ `active` の直下に配置する
+pub fn place_below(overlay: isize, active: isize) {
+    unsafe {
+        let _ = SetWindowPos(
+            HWND(overlay as *mut _),
+            Some(HWND(active as *mut _)),
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        );
+    }
+}
+
+/// ウィンドウ全体の不透明度（DWM の背景効果を薄くするのに使う）
+pub fn set_window_alpha(window: &WebviewWindow, opacity: f32) {
+    if let Some(hwnd) = hwnd(window) {
+        let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        unsafe {
+            let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), alpha, LWA_ALPHA);
+        }
+    }
+}
+
+/// 画面キャプチャに映らないようにする（ぼかしが自分自身を拾わないため）
+pub fn set_exclude_from_capture(
+    window: &WebviewWindow,
+    exclude: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hwnd = HWND(window.hwnd()?.0);
+    let affinity = if exclude {
+        WDA_EXCLUDEFROMCAPTURE
+    } else {
+        WDA_NONE
+    };
+    unsafe { SetWindowDisplayAffinity(hwnd, affinity)? };
+    Ok(())
+}
+
+/// ウィンドウがあるモニターの Windows.Graphics.Capture セッション
+pub fn capture_source(
+    window: &WebviewWindow,
+) -> Result<Option<Box<dyn FrameSource>>, Box<dyn std::error::Error>> {
+    let hwnd = HWND(window.hwnd()?.0);
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    let session = CaptureSession::new(monitor_capture_item(monitor)?)?;
+    Ok(Some(Box::new(session)))
+}
+
+/// CPU で描いた画素をレイヤードウィンドウの中身として出す `Presenter`
+pub fn layered_presenter(window: &WebviewWindow) -> Result<Presenter, Box<dyn std::error::Error>> {
+    let hwnd = HWND(window.hwnd()?.0);
+    // SetLayeredWindowAttributes で薄くしたことのあるウィンドウには UpdateLayeredWindow
+    // が効かないので、レイヤードの指定を付け直して素の状態に戻す
+    reset_layered(hwnd);
+    Ok(Box::new(move |pixels, width, height| {
+        Ok(present_layered(hwnd, pixels, width, height)?)
+    }))
+}
+
+/// この Windows のビルドでは使えない DWM の背景効果
+///
+/// 試してから落とすのでは実際に試した方式しか分からないので、設定 UI 向けに先に調べておく。
+pub fn unsupported_backdrops() -> Vec<OverlayBackend> {
+    let version = windows_version::OsVersion::current();
+    let unsupported: Vec<_> = OverlayBackend::SYSTEM_BACKDROPS
+        .into_iter()
+        .filter(|kind| !kind.supported_on_windows(version.major, version.minor, version.build))
+        .collect();
+    tracing::info!(
+        "Windows {}.{}.{}: unsupported backdrops {:?}",
+        version.major,
+        version.minor,
+        version.build,
+        unsupported
+    );
+    unsupported
+}
+
+/// DWM の背景効果（`kind` が OS の合成でぼかす方式のとき）
+pub fn system_backdrop(
+    kind: OverlayBackend,
+    window: &WebviewWindow,
+    settings: &Settings,
+) -> Result<Box<dyn BlurBackend>, Box<dyn std::error::Error>> {
+    let material = match kind {
+        OverlayBackend::SystemAcrylic => Material::Acrylic,
+        OverlayBackend::SystemMica => Material::Mica,
+        OverlayBackend::SystemTabbed => Material::Tabbed,
+        OverlayBackend::SystemBlur => Material::Blur,
+        _ => return Err(format!("{:?} is not a system backdrop", kind).into()),
+    };
+    Ok(Box::new(DwmBackdrop::new(
+        window.clone(),
+        material,
+        settings,
+    )))
+}
+
+fn reset_layered(hwnd: HWND) {
+    unsafe {
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE);
+        SetWindowLongW(hwnd, GWL_EXSTYLE, ex_style & !(WS_EX_LAYERED.0 as i32));
+        SetWindowLongW(hwnd, GWL_EXSTYLE, ex_style | WS_EX_LAYERED.0 as i32);
+    }
+}
+
+/// CPU で描いた画素（RGBA8、アルファ乗算済み）をレイヤードウィンドウの中身にする
+fn present_layered(
+    hwnd: HWND,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> windows::core::Result<()> {
+    unsafe {
+        let screen_dc = GetDC(None);
+        let memory_dc = CreateCompatibleDC(Some(screen_dc));
+        let info = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width as i32,
+                // 負の高さでトップダウン（左上から）の並びになる
+                biHeight: -(height as i32),
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut bits = std::ptr::null_mut();
+        let result = CreateDIBSection(Some(memory_dc), &info, DIB_RGB_COLORS, &mut bits, None, 0)
+            .and_then(|bitmap| {
+                // DIB は BGRA の並び
+                let dib = std::slice::from_raw_parts_mut(bits as *mut u8, pixels.len());
+                for (dst, src) in dib.chunks_exact_mut(4).zip(pixels.chunks_exact(4)) {
+                    dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+                }
+
+                let previous = SelectObject(memory_dc, bitmap.into());
+                let size = SIZE {
+                    cx: width as i32,
+                    cy: height as i32,
+                };
+                let blend = BLENDFUNCTION {
+                    BlendOp: AC_SRC_OVER as u8,
+                    BlendFlags: 0,
+                    SourceConstantAlpha: 255,
+                    AlphaFormat: AC_SRC_ALPHA as u8,
+                };
+                let result = UpdateLayeredWindow(
+                    hwnd,
+                    Some(screen_dc),
+                    None,
+                    Some(&size),
+                    Some(memory_dc),
+                    Some(&POINT::default()),
+                    COLORREF(0),
+                    Some(&blend),
+                    ULW_ALPHA,
+                );
+                SelectObject(memory_dc, previous);
+                let _ = DeleteObject(bitmap.into());
+                result
+            });
+        let _ = DeleteDC(memory_dc);
+        ReleaseDC(None, screen_dc);
+        result
+    }
+}
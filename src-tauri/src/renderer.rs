@@ -1,31 +1,393 @@
+pub mod cpu;
+#[cfg(test)]
+mod golden;
+
+use crate::capture::Frame;
+use crate::effects::EffectChain;
+use crate::geometry::MonitorGeometry;
+use crate::kawase::{self, KawasePlan, MAX_KAWASE_ITERATIONS};
+use crate::kernel::{self, GaussianKernel, GaussianPlan, MAX_DOWNSAMPLE_LEVELS, MAX_KERNEL_TAPS};
+use crate::settings::DEFAULT_BLUR_STRENGTH;
+use crate::window_manager::WindowInfo;
 use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
 use tauri::WebviewWindow;
 use wgpu::util::DeviceExt;
 
+/// シェーダーに渡せる穴の最大数（blur.wgsl の配列長と揃えること）
+pub const MAX_HOLES: usize = 16;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Params {
-    pub hole_rect: [f32; 4],
+    /// [left, top, right, bottom]（モニター相対の正規化座標）
+    pub holes: [[f32; 4]; MAX_HOLES],
+    /// 各穴の角丸半径（物理ピクセル）。uniform の配列要素は 16 バイト境界なので 4 つずつ詰める
+    pub hole_radii: [[f32; 4]; MAX_HOLES / 4],
+    /// 描画先の物理ピクセルサイズ（角丸・フェザーの計算に使う）
+    pub resolution: [f32; 2],
+    pub hole_count: u32,
     pub blur_strength: f32,
-    pub _padding: [f32; 3],
+    /// 穴の境界から完全にぼかすまでの幅（物理ピクセル）。0 なら境界で切り替わる
+    pub feather: f32,
+    /// 描画全体の不透明度（表示・非表示のフェードに使う）
+    pub opacity: f32,
+    pub _padding: [f32; 2],
+    /// ぼかしの後にかける効果
+    pub effects: EffectChain,
+}
+
+impl Params {
+    pub fn new(blur_strength: f32) -> Self {
+        Self {
+            holes: [[0.0; 4]; MAX_HOLES],
+            hole_radii: [[0.0; 4]; MAX_HOLES / 4],
+            resolution: [1.0, 1.0],
+            hole_count: 0,
+            blur_strength,
+            feather: 0.0,
+            opacity: 1.0,
+            _padding: [0.0; 2],
+            effects: EffectChain::default(),
+        }
+    }
+
+    /// ウィンドウ一覧（物理座標）を `monitor` 相対の正規化座標に変換して穴に設定する
+    ///
+    /// 穴は `monitor` の範囲に切り詰め、重ならないウィンドウは飛ばす。ただし角丸の半径分は
+    /// はみ出させて、モニターの境目で切れた辺に角丸が付かないようにする。
+    /// `MAX_HOLES` を超えた分は無視する。先頭（フォアグラウンド）ほど優先される。
+    pub fn set_holes(&mut self, windows: &[WindowInfo], monitor: &MonitorGeometry) {
+        self.resolution = [
+            monitor.rect.width().max(1) as f32,
+            monitor.rect.height().max(1) as f32,
+        ];
+        self.holes = [[0.0; 4]; MAX_HOLES];
+        self.hole_radii = [[0.0; 4]; MAX_HOLES / 4];
+        let clipped = windows.iter().filter_map(|window| {
+            monitor.clip(&window.rect)?;
+            let rect = window
+                .rect
+                .intersection(&monitor.rect.inflate(window.corner_radius))?;
+            Some((rect, window.corner_radius))
+        });
+        let mut count = 0;
+        for (i, (rect, radius)) in clipped.take(MAX_HOLES).enumerate() {
+            self.holes[i] = monitor.normalize(&rect).to_array();
+            self.hole_radii[i / 4][i % 4] = radius as f32;
+            count = i + 1;
+        }
+        self.hole_count = count as u32;
+    }
+
+    /// 正規化座標 `uv` におけるマスク値（CPU 参照実装。0 = 鮮明、1 = 完全にぼかす）
+    #[cfg(test)]
+    pub fn mask_at(&self, uv: [f32; 2]) -> f32 {
+        let count = (self.hole_count as usize).min(MAX_HOLES);
+        let radii: Vec<f32> = self.hole_radii.iter().flatten().copied().collect();
+        crate::mask::hole_mask(
+            uv,
+            &self.holes[..count],
+            &radii[..count],
+            self.resolution,
+            self.feather,
+        )
+    }
+}
+
+/// ぼかしの方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlurMode {
+    /// 水平・垂直 2 パスのガウスぼかし
+    #[default]
+    Gaussian,
+    /// 縮小・拡大を繰り返す Dual-Kawase（4K で大きな半径を使うとき向け）
+    DualKawase,
+}
+
+/// ぼかしの中間レンダーターゲットの形式（sRGB なので線形空間でぼかされる）
+const INTERMEDIATE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// gaussian.wgsl の 1 パス分のパラメータ
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct BlurPassParams {
+    /// 1 タップあたりの移動量（UV）
+    pub direction: [f32; 2],
+    /// 中心を除く片側のタップ数
+    pub tap_count: u32,
+    pub _padding: u32,
+    /// 中心から順に 4 つずつ詰めた重み
+    pub weights: [[f32; 4]; MAX_KERNEL_TAPS / 4 + 1],
+}
+
+impl BlurPassParams {
+    /// `kernel` を `direction`（単位ベクトル）方向に、`size` のテクスチャへ適用するパラメータ
+    pub fn new(kernel: &GaussianKernel, direction: [f32; 2], size: (u32, u32)) -> Self {
+        let mut weights = [[0.0; 4]; MAX_KERNEL_TAPS / 4 + 1];
+        for (i, &w) in kernel.weights.iter().take(MAX_KERNEL_TAPS + 1).enumerate() {
+            weights[i / 4][i % 4] = w;
+        }
+        Self {
+            direction: [
+                direction[0] * kernel.step / size.0.max(1) as f32,
+                direction[1] * kernel.step / size.1.max(1) as f32,
+            ],
+            tap_count: kernel.taps().min(MAX_KERNEL_TAPS) as u32,
+            _padding: 0,
+            weights,
+        }
+    }
+}
+
+/// kawase.wgsl の 1 パス分のパラメータ
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct KawasePassParams {
+    /// 出力サイズの半ピクセル（UV）にオフセット倍率を掛けたもの
+    pub half_pixel: [f32; 2],
+    pub _padding: [f32; 2],
+}
+
+/// 描画先にもサンプリング元にもなるテクスチャ
+pub struct RenderTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl RenderTarget {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        label: &str,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: INTERMEDIATE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some(label),
+        });
+        Self {
+            texture,
+            view,
+            bind_group,
+        }
+    }
+}
+
+// ガウスぼかしの縮小には Dual-Kawase の縮小チェーンを使い回す
+const _: () = assert!(MAX_DOWNSAMPLE_LEVELS <= MAX_KAWASE_ITERATIONS);
+
+/// 水平パスと垂直パスそれぞれの出力先
+///
+/// 水平パスは縮小した解像度のまま描くので、縮小段数ごとに出力先を持つ
+/// （`horizontal[i]` は元の 1/2^i のサイズ）。垂直パスは拡大を兼ねて元のサイズに描く。
+pub struct BlurTargets {
+    pub horizontal: Vec<RenderTarget>,
+    pub vertical: RenderTarget,
+}
+
+impl BlurTargets {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self {
+            horizontal: (0..=MAX_DOWNSAMPLE_LEVELS)
+                .map(|i| {
+                    RenderTarget::new(
+                        device,
+                        layout,
+                        sampler,
+                        "blur_horizontal",
+                        (width >> i).max(1),
+                        (height >> i).max(1),
+                    )
+                })
+                .collect(),
+            vertical: RenderTarget::new(device, layout, sampler, "blur_vertical", width, height),
+        }
+    }
+}
+
+/// Dual-Kawase の縮小チェーン（`levels[i]` は元の 1/2^(i+1) のサイズ）。ガウスぼかしの縮小にも使う
+pub struct KawaseChain {
+    pub levels: Vec<RenderTarget>,
+}
+
+impl KawaseChain {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let levels = (1..=MAX_KAWASE_ITERATIONS)
+            .map(|i| {
+                RenderTarget::new(
+                    device,
+                    layout,
+                    sampler,
+                    "kawase_level",
+                    (width >> i).max(1),
+                    (height >> i).max(1),
+                )
+            })
+            .collect();
+        Self { levels }
+    }
+}
+
+/// 中間テクスチャへ描画するぼかしパイプライン（ブレンドなし）
+fn create_blur_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    entry_point: &str,
+    label: &str,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: Some(entry_point),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: INTERMEDIATE_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// 描画結果の出力先
+pub enum Output {
+    /// オーバーレイウィンドウのサーフェス
+    Surface {
+        surface: wgpu::Surface<'static>,
+        config: wgpu::SurfaceConfiguration,
+    },
+    /// ウィンドウを持たないオフスクリーンのテクスチャ（`read_output` で読み戻せる）
+    #[cfg(test)]
+    Texture(wgpu::Texture),
+}
+
+/// オフスクリーン描画の出力形式（PNG などにそのまま書ける RGBA）
+#[cfg(test)]
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// ぼかし・効果・穴抜きの描画（wgpu の `Renderer` と CPU の `cpu::CpuRenderer`）
+///
+/// どちらも同じ `Params` からほぼ同じ画を描く。描画スレッドはアダプターが見つからなければ
+/// CPU 実装に切り替えるので、呼び出し側はこのトレイト越しに扱う。
+pub trait FrameRenderer {
+    /// 出力先の物理ピクセルサイズ
+    fn size(&self) -> (u32, u32);
+
+    /// 出力先のサイズを変える。幅か高さが 0 なら何もしない
+    fn resize(&mut self, width: u32, height: u32);
+
+    fn set_blur_mode(&mut self, mode: BlurMode);
+
+    fn set_scale_factor(&mut self, scale_factor: f64);
+
+    /// キャプチャしたフレームを描画元にする
+    ///
+    /// `source_offset` はキャプチャ画像内で描画先の左上に当たる位置（物理ピクセル）。
+    /// キャプチャ側がはみ出す分は切り捨てる。
+    fn upload_frame(&mut self, frame: &Frame, source_offset: (u32, u32));
+
+    /// ぼかし・効果・穴抜きを出力先に描画する
+    fn render(&mut self, params: &Params) -> Result<(), Box<dyn std::error::Error>>;
 }
 
 pub struct Renderer {
-    pub surface: wgpu::Surface<'static>,
+    pub output: Output,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
-    pub config: wgpu::SurfaceConfiguration,
+    /// 出力先の物理ピクセルサイズ
+    pub width: u32,
+    pub height: u32,
+    /// ぼかしの強さ（論理ピクセル）を物理ピクセルに換算するスケール
+    pub scale_factor: f64,
     pub render_pipeline: wgpu::RenderPipeline,
+    pub blur_pipeline: wgpu::RenderPipeline,
+    pub kawase_down_pipeline: wgpu::RenderPipeline,
+    pub kawase_up_pipeline: wgpu::RenderPipeline,
+    pub blur_mode: BlurMode,
+    pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    pub sampler: wgpu::Sampler,
     pub diffuse_bind_group: wgpu::BindGroup,
     pub diffuse_texture: wgpu::Texture,
     pub uniform_buffer: wgpu::Buffer,
     pub uniform_bind_group: wgpu::BindGroup,
-    pub window: Arc<WebviewWindow>,
+    pub blur_targets: BlurTargets,
+    /// 水平パス・垂直パス・縮小パスのパラメータ（同じフレーム内で別の値を使うのでバッファを分ける）
+    pub blur_pass_buffers: [wgpu::Buffer; 3],
+    pub blur_pass_bind_groups: [wgpu::BindGroup; 3],
+    pub kawase_chain: KawaseChain,
+    /// 縮小パス i と拡大パス i（`MAX_KAWASE_ITERATIONS + i`）のパラメータ
+    pub kawase_pass_buffers: Vec<wgpu::Buffer>,
+    pub kawase_pass_bind_groups: Vec<wgpu::BindGroup>,
+    /// 描画したフレーム数（ノイズのパターンを変えるのに使う）
+    pub frame_index: u32,
 }
 
 impl Renderer {
-    pub async fn new(window: Arc<WebviewWindow>) -> Result<Self, Box<dyn std::error::Error>> {
+    /// `window` のサーフェスに描画するレンダラーを作る
+    ///
+    /// サイズとスケールは呼び出し側がメインスレッドで読んで渡す。
+    pub async fn new(
+        window: Arc<WebviewWindow>,
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
             ..Default::default()
@@ -52,7 +414,6 @@ impl Renderer {
             })
             .await?;
 
-        let size = window.inner_size()?;
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps
             .formats
@@ -74,13 +435,13 @@ impl Renderer {
         } else {
             surface_caps.alpha_modes[0]
         };
-        println!("Selected alpha_mode: {:?}", alpha_mode);
+        tracing::info!("Selected alpha_mode: {:?}", alpha_mode);
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
-            width: size.width.max(1),
-            height: size.height.max(1),
+            width: width.max(1),
+            height: height.max(1),
             present_mode: surface_caps.present_modes[0],
             alpha_mode,
             view_formats: vec![],
@@ -88,24 +449,67 @@ impl Renderer {
         };
         surface.configure(&device, &config);
 
-        let texture_size = wgpu::Extent3d {
-            width: size.width.max(1),
-            height: size.height.max(1),
-            depth_or_array_layers: 1,
-        };
-        let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("diffuse_texture"),
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Bgra8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
+        let format = config.format;
+        Ok(Self::with_output(
+            device,
+            queue,
+            Output::Surface { surface, config },
+            format,
+            width,
+            height,
+            scale_factor,
+        ))
+    }
+
+    /// ウィンドウを使わず、`width` x `height` のテクスチャに描画するレンダラーを作る
+    ///
+    /// `force_fallback_adapter` を立てるとソフトウェア実装（lavapipe / WARP など）を使う。
+    /// GPU の無い CI でもシェーダーを通して描画結果を確かめられる。
+    #[cfg(test)]
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
         });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default())
+            .await?;
 
-        let diffuse_texture_view =
-            diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let texture = create_output_texture(&device, width, height);
+        Ok(Self::with_output(
+            device,
+            queue,
+            Output::Texture(texture),
+            OFFSCREEN_FORMAT,
+            width,
+            height,
+            scale_factor,
+        ))
+    }
+
+    /// 出力先に依存しない部分（中間テクスチャ・パイプライン）を用意する
+    fn with_output(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        output: Output,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    ) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
         let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
@@ -139,26 +543,30 @@ impl Renderer {
                 label: Some("texture_bind_group_layout"),
             });
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+        let (diffuse_texture, diffuse_bind_group) = create_diffuse_texture(
+            &device,
+            &texture_bind_group_layout,
+            &diffuse_sampler,
+            width,
+            height,
+        );
 
-        let params = Params {
-            hole_rect: [0.0, 0.0, 0.0, 0.0],
-            blur_strength: 30.0,
-            _padding: [0.0; 3],
-        };
+        let blur_targets = BlurTargets::new(
+            &device,
+            &texture_bind_group_layout,
+            &diffuse_sampler,
+            width,
+            height,
+        );
+        let kawase_chain = KawaseChain::new(
+            &device,
+            &texture_bind_group_layout,
+            &diffuse_sampler,
+            width,
+            height,
+        );
+
+        let params = Params::new(DEFAULT_BLUR_STRENGTH);
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
             contents: bytemuck::cast_slice(&[params]),
@@ -189,6 +597,54 @@ impl Renderer {
             label: Some("uniform_bind_group"),
         });
 
+        let blur_pass_buffers = [
+            "Blur Pass Horizontal",
+            "Blur Pass Vertical",
+            "Blur Pass Downsample",
+        ]
+        .map(|label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: std::mem::size_of::<BlurPassParams>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        });
+        let blur_pass_bind_groups = [0, 1, 2].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &uniform_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: blur_pass_buffers[i].as_entire_binding(),
+                }],
+                label: Some("blur_pass_bind_group"),
+            })
+        });
+
+        let kawase_pass_buffers: Vec<wgpu::Buffer> = (0..MAX_KAWASE_ITERATIONS * 2)
+            .map(|_| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Kawase Pass"),
+                    size: std::mem::size_of::<KawasePassParams>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+        let kawase_pass_bind_groups = kawase_pass_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &uniform_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                    label: Some("kawase_pass_bind_group"),
+                })
+            })
+            .collect();
+
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("blur.wgsl").into()),
@@ -214,7 +670,7 @@ impl Renderer {
                 module: &shader,
                 entry_point: Some("fs_main"),
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
+                    format,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -227,118 +683,223 @@ impl Renderer {
             cache: None,
         });
 
-        Ok(Self {
-            surface,
+        // 水平・垂直で共通のぼかしパイプライン（方向は BlurPassParams で切り替える）
+        let blur_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Gaussian Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gaussian.wgsl").into()),
+        });
+        let blur_pipeline = create_blur_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &blur_shader,
+            "fs_main",
+            "Blur Pipeline",
+        );
+
+        let kawase_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Kawase Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("kawase.wgsl").into()),
+        });
+        let kawase_down_pipeline = create_blur_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &kawase_shader,
+            "fs_down",
+            "Kawase Down Pipeline",
+        );
+        let kawase_up_pipeline = create_blur_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &kawase_shader,
+            "fs_up",
+            "Kawase Up Pipeline",
+        );
+
+        Self {
+            output,
             device,
             queue,
-            config,
+            width,
+            height,
+            scale_factor,
             render_pipeline,
+            blur_pipeline,
+            kawase_down_pipeline,
+            kawase_up_pipeline,
+            blur_mode: BlurMode::default(),
+            texture_bind_group_layout,
+            sampler: diffuse_sampler,
             diffuse_bind_group,
             diffuse_texture,
             uniform_buffer,
             uniform_bind_group,
-            window,
-        })
-    }
-
-    pub fn resize(&mut self, new_size: tauri::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-
-            // テクスチャの再生成
-            let texture_size = wgpu::Extent3d {
-                width: new_size.width,
-                height: new_size.height,
-                depth_or_array_layers: 1,
-            };
-            self.diffuse_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("diffuse_texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                view_formats: &[],
-            });
-
-            let diffuse_texture_view = self
-                .diffuse_texture
-                .create_view(&wgpu::TextureViewDescriptor::default());
-            let diffuse_sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::ClampToEdge,
-                address_mode_v: wgpu::AddressMode::ClampToEdge,
-                address_mode_w: wgpu::AddressMode::ClampToEdge,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Linear,
-                mipmap_filter: wgpu::FilterMode::Nearest,
-                ..Default::default()
-            });
-
-            let texture_bind_group_layout =
-                self.device
-                    .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                        entries: &[
-                            wgpu::BindGroupLayoutEntry {
-                                binding: 0,
-                                visibility: wgpu::ShaderStages::FRAGMENT,
-                                ty: wgpu::BindingType::Texture {
-                                    multisampled: false,
-                                    view_dimension: wgpu::TextureViewDimension::D2,
-                                    sample_type: wgpu::TextureSampleType::Float {
-                                        filterable: true,
-                                    },
-                                },
-                                count: None,
-                            },
-                            wgpu::BindGroupLayoutEntry {
-                                binding: 1,
-                                visibility: wgpu::ShaderStages::FRAGMENT,
-                                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                                count: None,
-                            },
-                        ],
-                        label: None,
-                    });
-
-            self.diffuse_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
-                    },
-                ],
-                label: Some("diffuse_bind_group"),
-            });
+            blur_targets,
+            blur_pass_buffers,
+            blur_pass_bind_groups,
+            kawase_chain,
+            kawase_pass_buffers,
+            kawase_pass_bind_groups,
+            frame_index: 0,
         }
     }
 
-    pub fn render(&mut self, params: &Params) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// ぼかし・効果・穴抜きを出力先に描画する（サーフェスのエラーをそのまま返す）
+    pub fn draw(&mut self, params: &Params) -> Result<(), wgpu::SurfaceError> {
+        let (frame, view) = match &self.output {
+            Output::Surface { surface, .. } => {
+                let frame = surface.get_current_texture()?;
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            #[cfg(test)]
+            Output::Texture(texture) => (
+                None,
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            ),
+        };
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
 
+        // ノイズは毎フレーム違うパターンにする（静止画にすると模様に見えてしまう）
+        let mut params = *params;
+        self.frame_index = self.frame_index.wrapping_add(1);
+        params.effects.noise_seed = (self.frame_index % 1024) as f32;
         self.queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[*params]));
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[params]));
 
-        {
+        // 論理ピクセル基準の強さから物理ピクセルの半径を決める
+        let sigma = kernel::sigma_for_strength(params.blur_strength, self.scale_factor);
+        let plan = KawasePlan::new(sigma * 3.0);
+        let use_kawase = self.blur_mode == BlurMode::DualKawase && plan.iterations > 0;
+
+        let mut passes: Vec<(
+            &str,
+            &wgpu::RenderPipeline,
+            &wgpu::TextureView,
+            &wgpu::BindGroup,
+            &wgpu::BindGroup,
+        )> = Vec::new();
+
+        if use_kawase {
+            // キャプチャ → 縮小 n 段 → 拡大 n 段（最後は等倍の中間テクスチャへ）
+            let levels = &self.kawase_chain.levels[..plan.iterations];
+            for (i, level) in levels.iter().enumerate() {
+                let texture = &level.texture;
+                let pass = KawasePassParams {
+                    half_pixel: kawase::half_pixel(texture.width(), texture.height(), plan.offset),
+                    _padding: [0.0; 2],
+                };
+                self.queue.write_buffer(
+                    &self.kawase_pass_buffers[i],
+                    0,
+                    bytemuck::cast_slice(&[pass]),
+                );
+                let source = match i {
+                    0 => &self.diffuse_bind_group,
+                    _ => &levels[i - 1].bind_group,
+                };
+                passes.push((
+                    "Kawase Down Pass",
+                    &self.kawase_down_pipeline,
+                    &level.view,
+                    source,
+                    &self.kawase_pass_bind_groups[i],
+                ));
+            }
+            for (i, level) in levels.iter().enumerate().rev() {
+                let target = match i {
+                    0 => &self.blur_targets.vertical,
+                    _ => &levels[i - 1],
+                };
+                let pass = KawasePassParams {
+                    half_pixel: kawase::half_pixel(
+                        target.texture.width(),
+                        target.texture.height(),
+                        plan.offset,
+                    ),
+                    _padding: [0.0; 2],
+                };
+                let index = MAX_KAWASE_ITERATIONS + i;
+                self.queue.write_buffer(
+                    &self.kawase_pass_buffers[index],
+                    0,
+                    bytemuck::cast_slice(&[pass]),
+                );
+                passes.push((
+                    "Kawase Up Pass",
+                    &self.kawase_up_pipeline,
+                    &target.view,
+                    &level.bind_group,
+                    &self.kawase_pass_bind_groups[index],
+                ));
+            }
+        } else {
+            // キャプチャ → 縮小 n 段 → 水平ぼかし（縮小したまま）→ 垂直ぼかし（元のサイズへ拡大）
+            let GaussianPlan { levels, kernel } = GaussianPlan::new(sigma);
+            let downsampled = &self.kawase_chain.levels[..levels];
+            let horizontal = &self.blur_targets.horizontal[levels];
+            let size = |target: &RenderTarget| (target.texture.width(), target.texture.height());
+            let source_size = downsampled.last().map_or((self.width, self.height), size);
+            let pass_params = [
+                BlurPassParams::new(&kernel, [1.0, 0.0], source_size),
+                BlurPassParams::new(&kernel, [0.0, 1.0], size(horizontal)),
+                // タップなしでテクセルの境目をサンプリングすると 2x2 の平均になる
+                BlurPassParams::new(&GaussianKernel::new(0.0), [0.0, 0.0], source_size),
+            ];
+            for (buffer, pass) in self.blur_pass_buffers.iter().zip(pass_params) {
+                self.queue
+                    .write_buffer(buffer, 0, bytemuck::cast_slice(&[pass]));
+            }
+            for (i, level) in downsampled.iter().enumerate() {
+                let source = match i {
+                    0 => &self.diffuse_bind_group,
+                    _ => &downsampled[i - 1].bind_group,
+                };
+                passes.push((
+                    "Blur Downsample Pass",
+                    &self.blur_pipeline,
+                    &level.view,
+                    source,
+                    &self.blur_pass_bind_groups[2],
+                ));
+            }
+            passes.push((
+                "Blur Horizontal Pass",
+                &self.blur_pipeline,
+                &horizontal.view,
+                downsampled
+                    .last()
+                    .map_or(&self.diffuse_bind_group, |level| &level.bind_group),
+                &self.blur_pass_bind_groups[0],
+            ));
+            passes.push((
+                "Blur Vertical Pass",
+                &self.blur_pipeline,
+                &self.blur_targets.vertical.view,
+                &horizontal.bind_group,
+                &self.blur_pass_bind_groups[1],
+            ));
+        }
+
+        // 最後に穴を抜いて出力先へ
+        passes.push((
+            "Render Pass",
+            &self.render_pipeline,
+            &view,
+            &self.blur_targets.vertical.bind_group,
+            &self.uniform_bind_group,
+        ));
+
+        for (label, pipeline, target, source, uniforms) in passes {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
+                label: Some(label),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view: target,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
@@ -349,15 +910,370 @@ impl Renderer {
                 ..Default::default()
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.uniform_bind_group, &[]);
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, source, &[]);
+            render_pass.set_bind_group(1, uniforms, &[]);
             render_pass.draw(0..6, 0..1);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        if let Some(frame) = frame {
+            frame.present();
+        }
 
         Ok(())
     }
+
+    /// オフスクリーンの出力を RGBA8（行の詰め物なし、左上から）で読み戻す
+    #[cfg(test)]
+    pub fn read_output(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let Output::Texture(texture) = &self.output else {
+            return Err("read_output requires an offscreen renderer".into());
+        };
+        let unpadded_row = self.width * 4;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: u64::from(padded_row * self.height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        rx.recv()??;
+
+        let data = slice.get_mapped_range();
+        let pixels = data
+            .chunks(padded_row as usize)
+            .flat_map(|row| &row[..unpadded_row as usize])
+            .copied()
+            .collect();
+        drop(data);
+        buffer.unmap();
+        Ok(pixels)
+    }
+}
+
+impl FrameRenderer for Renderer {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.width = width;
+            self.height = height;
+            match &mut self.output {
+                Output::Surface { surface, config } => {
+                    config.width = width;
+                    config.height = height;
+                    surface.configure(&self.device, config);
+                }
+                #[cfg(test)]
+                Output::Texture(texture) => {
+                    *texture = create_output_texture(&self.device, self.width, self.height);
+                }
+            }
+
+            // サイズに依存するテクスチャだけ作り直す（レイアウトとサンプラーはパイプラインと共有）
+            (self.diffuse_texture, self.diffuse_bind_group) = create_diffuse_texture(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &self.sampler,
+                width,
+                height,
+            );
+
+            self.blur_targets = BlurTargets::new(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &self.sampler,
+                width,
+                height,
+            );
+            self.kawase_chain = KawaseChain::new(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &self.sampler,
+                width,
+                height,
+            );
+        }
+    }
+
+    /// キャプチャしたフレームを `diffuse_texture` に書き込む
+    fn upload_frame(&mut self, frame: &Frame, source_offset: (u32, u32)) {
+        let (width, height) = frame.size();
+        if width == 0 || height == 0 {
+            return;
+        }
+        // 描画先がモニターの一部（作業領域など）なら、その位置から切り出す
+        let (offset_x, offset_y) = (
+            source_offset.0.min(width - 1),
+            source_offset.1.min(height - 1),
+        );
+        let copy_size = wgpu::Extent3d {
+            width: (width - offset_x).min(self.diffuse_texture.width()),
+            height: (height - offset_y).min(self.diffuse_texture.height()),
+            depth_or_array_layers: 1,
+        };
+        let destination = wgpu::TexelCopyTextureInfo {
+            texture: &self.diffuse_texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        };
+
+        match frame {
+            Frame::Cpu {
+                row_pitch, data, ..
+            } => {
+                let data = &data[(offset_y * row_pitch + offset_x * 4) as usize..];
+                self.queue.write_texture(
+                    destination,
+                    data,
+                    wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(*row_pitch),
+                        rows_per_image: Some(height - offset_y),
+                    },
+                    copy_size,
+                );
+            }
+            Frame::Gpu(texture) => {
+                // sRGB かどうかだけが違う形式同士なので GPU 上でそのままコピーできる
+                let mut encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("Capture Upload Encoder"),
+                        });
+                encoder.copy_texture_to_texture(
+                    wgpu::TexelCopyTextureInfo {
+                        texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d {
+                            x: offset_x,
+                            y: offset_y,
+                            z: 0,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    destination,
+                    copy_size,
+                );
+                self.queue.submit(std::iter::once(encoder.finish()));
+            }
+        }
+    }
+
+    fn set_blur_mode(&mut self, mode: BlurMode) {
+        self.blur_mode = mode;
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn render(&mut self, params: &Params) -> Result<(), Box<dyn std::error::Error>> {
+        match self.draw(params) {
+            Ok(()) => Ok(()),
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                tracing::debug!("Surface lost, reconfiguring");
+                self.resize(self.width, self.height);
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// キャプチャを書き込むテクスチャと、それを描画元にするバインドグループ
+fn create_diffuse_texture(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    width: u32,
+    height: u32,
+) -> (wgpu::Texture, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("diffuse_texture"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        label: Some("diffuse_bind_group"),
+    });
+    (texture, bind_group)
+}
+
+/// オフスクリーン描画の出力先テクスチャ
+#[cfg(test)]
+fn create_output_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("offscreen_output"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OFFSCREEN_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// GPU を使うテストの下準備
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::Renderer;
+
+    /// ソフトウェアのアダプター（lavapipe / WARP）でオフスクリーンに描く `Renderer`
+    ///
+    /// アダプターが無い環境ではテストを失敗させる（黙って飛ばすと何も確かめずに通ってしまう）。
+    pub fn headless(width: u32, height: u32) -> Renderer {
+        pollster::block_on(Renderer::new_headless(width, height, 1.0, true)).unwrap_or_else(|e| {
+            panic!(
+                "no fallback adapter for GPU tests ({}); install Mesa's lavapipe (Vulkan) on Linux",
+                e
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::synthetic::SyntheticFrameSource;
+    use crate::capture::FrameSource;
+
+    /// 縮めたり広げたりした後も、出力のサイズと穴の位置が今のサイズに合っているか
+    #[test]
+    fn renders_after_resizing() {
+        let mut renderer = testing::headless(64, 48);
+        let mut source = SyntheticFrameSource::new(200, 120);
+        let Some(frame) = source.next_frame(None).unwrap() else {
+            unreachable!("synthetic source always returns a frame");
+        };
+
+        // 0x0 は最小化などで来るが無視して直前のサイズのまま描く
+        let steps = [
+            (64, 48),
+            (0, 0),
+            (16, 8),
+            (0, 30),
+            (200, 120),
+            (1, 1),
+            (33, 17),
+        ];
+        let mut expected = (64, 48);
+        for mode in [BlurMode::Gaussian, BlurMode::DualKawase] {
+            renderer.set_blur_mode(mode);
+            for (width, height) in steps {
+                renderer.resize(width, height);
+                if width > 0 && height > 0 {
+                    expected = (width, height);
+                }
+                assert_eq!(
+                    renderer.size(),
+                    expected,
+                    "{:?} after {}x{}",
+                    mode,
+                    width,
+                    height
+                );
+
+                let (width, height) = expected;
+                let mut params = Params::new(12.0);
+                params.resolution = [width as f32, height as f32];
+                params.hole_count = 1;
+                params.holes[0] = [0.0, 0.0, 0.5, 1.0];
+                renderer.upload_frame(&frame, (0, 0));
+                renderer.render(&params).unwrap();
+
+                let pixels = renderer.read_output().unwrap();
+                assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+                // 最初からこのサイズで作ったものと同じ画になる（古いサイズの中間テクスチャが残っていない）
+                let mut fresh = testing::headless(width, height);
+                fresh.set_blur_mode(mode);
+                fresh.upload_frame(&frame, (0, 0));
+                fresh.render(&params).unwrap();
+                assert!(
+                    pixels == fresh.read_output().unwrap(),
+                    "{:?} {}x{} differs from a fresh renderer",
+                    mode,
+                    width,
+                    height
+                );
+                // 左半分が穴（透明）、右半分はぼかした画で不透明
+                for y in 0..height {
+                    for x in 0..width {
+                        let alpha = pixels[((y * width + x) * 4 + 3) as usize];
+                        if x + 1 < width / 2 {
+                            assert_eq!(
+                                alpha, 0,
+                                "{:?} {}x{} at ({}, {})",
+                                mode, width, height, x, y
+                            );
+                        } else if x > width / 2 {
+                            assert_eq!(
+                                alpha, 255,
+                                "{:?} {}x{} at ({}, {})",
+                                mode, width, height, x, y
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,523 @@
+//! GPU を使わないぼかしの描画
+//!
+//! アダプターが見つからない環境（GPU の無い CI やシンクライアント）向けに、`Renderer` と
+//! 同じ `Params` から同じ画を CPU で描く。ガウスぼかしはサンプリングを整数の添字と重みの表に
+//! 直した行・列のパスで行い（行ごとに rayon で並列化する）、Dual-Kawase・効果・穴抜きは
+//! `kawase` / `effects` / `mask` の参照実装をそのまま使う。GPU 版とは中間テクスチャの量子化の
+//! 分だけ値がずれる。
+
+use super::{BlurMode, FrameRenderer, Params, MAX_HOLES};
+use crate::capture::Frame;
+use crate::effects;
+use crate::kawase::{self, Image, KawasePlan};
+use crate::kernel::{self, GaussianKernel, GaussianPlan};
+use crate::mask;
+use rayon::prelude::*;
+use std::sync::OnceLock;
+
+/// 描き終えた画素（RGBA8、sRGB・アルファ乗算済み）と幅・高さを受け取って画面に出す
+pub type Presenter = Box<dyn FnMut(&[u8], u32, u32) -> Result<(), Box<dyn std::error::Error>>>;
+
+pub struct CpuRenderer {
+    /// 出力先の物理ピクセルサイズ
+    pub width: u32,
+    pub height: u32,
+    /// ぼかしの強さ（論理ピクセル）を物理ピクセルに換算するスケール
+    pub scale_factor: f64,
+    pub blur_mode: BlurMode,
+    /// 描画のたびに結果を渡す先。`None` なら `pixels` で読み出すだけ
+    pub present: Option<Presenter>,
+    /// 描画したフレーム数（ノイズのパターンを変えるのに使う）
+    pub frame_index: u32,
+    /// 最後に受け取ったキャプチャ（線形 RGBA）
+    source: Image,
+    /// 描画結果（`OFFSCREEN_FORMAT` の `read_output` と同じ並び）
+    pixels: Vec<u8>,
+}
+
+impl CpuRenderer {
+    pub fn new(width: u32, height: u32, scale_factor: f64) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        Self {
+            width,
+            height,
+            scale_factor,
+            blur_mode: BlurMode::default(),
+            present: None,
+            frame_index: 0,
+            source: Image::new(width, height),
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    /// 最後に描いた結果（RGBA8、行の詰め物なし、左上から）
+    #[cfg(test)]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+impl FrameRenderer for CpuRenderer {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        self.source = Image::new(width, height);
+        self.pixels = vec![0; (width * height * 4) as usize];
+    }
+
+    fn set_blur_mode(&mut self, mode: BlurMode) {
+        self.blur_mode = mode;
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn upload_frame(&mut self, frame: &Frame, source_offset: (u32, u32)) {
+        let Frame::Cpu {
+            width,
+            height,
+            row_pitch,
+            data,
+        } = *frame
+        else {
+            // デバイスを渡さなければキャプチャは CPU フレームしか返さない
+            tracing::warn!("CPU renderer cannot read GPU frames");
+            return;
+        };
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (offset_x, offset_y) = (
+            source_offset.0.min(width - 1),
+            source_offset.1.min(height - 1),
+        );
+        let copy_width = (width - offset_x).min(self.width) as usize;
+        let copy_height = (height - offset_y).min(self.height) as usize;
+        let decode = srgb_decode_table();
+
+        let rows = self.source.pixels.par_chunks_mut(self.width as usize);
+        rows.take(copy_height).enumerate().for_each(|(y, row)| {
+            let start = ((offset_y as usize + y) * row_pitch as usize) + offset_x as usize * 4;
+            let line = &data[start..start + copy_width * 4];
+            for (pixel, bgra) in row.iter_mut().zip(line.chunks_exact(4)) {
+                *pixel = [
+                    decode[bgra[2] as usize],
+                    decode[bgra[1] as usize],
+                    decode[bgra[0] as usize],
+                    bgra[3] as f32 / 255.0,
+                ];
+            }
+        });
+    }
+
+    fn render(&mut self, params: &Params) -> Result<(), Box<dyn std::error::Error>> {
+        // ノイズは毎フレーム違うパターンにする（GPU 版と同じ種の進め方）
+        let mut params = *params;
+        self.frame_index = self.frame_index.wrapping_add(1);
+        params.effects.noise_seed = (self.frame_index % 1024) as f32;
+
+        let sigma = kernel::sigma_for_strength(params.blur_strength, self.scale_factor);
+        let plan = KawasePlan::new(sigma * 3.0);
+        let blurred = if self.blur_mode == BlurMode::DualKawase && plan.iterations > 0 {
+            kawase::blur(&self.source, plan)
+        } else {
+            gaussian_blur(&self.source, sigma)
+        };
+
+        composite(&blurred, &params, &mut self.pixels);
+        if let Some(present) = self.present.as_mut() {
+            present(&self.pixels, self.width, self.height)?;
+        }
+        Ok(())
+    }
+}
+
+/// 出力の画素ごとの、入力の軸上の（添字, 重み）の一覧
+type Taps = Vec<Vec<(usize, f32)>>;
+
+/// 長さ `src` の軸を長さ `dst` に描き直すときの重みの表
+///
+/// 出力の画素中心から `offsets`（入力のテクセル単位のずれ, 重み）だけずらした位置を
+/// ClampToEdge・線形補間で読む。`Image::sample` の 1 軸分で、バイリニアのサンプリングも
+/// タップの合計も軸ごとに分けられるので、行・列のパスは表どおりに足すだけになる。
+/// 整数位置に当たるタップ（縮小しないときのガウスぼかし）は重み 0 の側を落とすので 1 読みで済む。
+fn axis_taps(src: u32, dst: u32, offsets: &[(f32, f32)]) -> Taps {
+    let last = (src - 1) as f32;
+    let scale = src as f32 / dst as f32;
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) * scale - 0.5;
+            let mut taps = Vec::with_capacity(offsets.len() * 2);
+            for &(offset, weight) in offsets {
+                let x = center + offset;
+                let x0 = x.floor();
+                let f = x - x0;
+                for (x, w) in [(x0, 1.0 - f), (x0 + 1.0, f)] {
+                    if w > 0.0 {
+                        taps.push((x.clamp(0.0, last) as usize, weight * w));
+                    }
+                }
+            }
+            taps
+        })
+        .collect()
+}
+
+/// ガウスカーネルを `axis_taps` の `offsets` の形にする（中心と、その両側に `step` おき）
+fn kernel_offsets(kernel: &GaussianKernel) -> Vec<(f32, f32)> {
+    let mut offsets = vec![(0.0, kernel.weights[0])];
+    for (i, &w) in kernel.weights.iter().enumerate().skip(1) {
+        let offset = kernel.step * i as f32;
+        offsets.extend([(offset, w), (-offset, w)]);
+    }
+    offsets
+}
+
+/// 各行を `taps`（出力の列ごとの表）に従って描き直す。出力の幅は `taps.len()`
+fn filter_rows(source: &Image, taps: &[Vec<(usize, f32)>]) -> Image {
+    let width = taps.len();
+    let mut out = Image::new(width as u32, source.height);
+    out.pixels
+        .par_chunks_mut(width)
+        .zip(source.pixels.par_chunks(source.width as usize))
+        .for_each(|(row, line)| {
+            for (pixel, taps) in row.iter_mut().zip(taps) {
+                let mut color = [0.0; 4];
+                for &(x, w) in taps {
+                    for (acc, value) in color.iter_mut().zip(line[x]) {
+                        *acc += value * w;
+                    }
+                }
+                *pixel = color;
+            }
+        });
+    out
+}
+
+/// 各列を `taps`（出力の行ごとの表）に従って描き直す。出力の高さは `taps.len()`
+///
+/// 出力の 1 行は入力の行を丸ごと重み付きで足し合わせたものなので、連続したメモリを頭から
+/// 順に読み書きするだけになる。
+fn filter_columns(source: &Image, taps: &[Vec<(usize, f32)>]) -> Image {
+    let width = source.width as usize;
+    let mut out = Image::new(source.width, taps.len() as u32);
+    out.pixels
+        .par_chunks_mut(width)
+        .zip(taps)
+        .for_each(|(row, taps)| {
+            for &(y, w) in taps {
+                let line = &source.pixels[y * width..(y + 1) * width];
+                for (pixel, value) in row.iter_mut().zip(line) {
+                    for (acc, value) in pixel.iter_mut().zip(value) {
+                        *acc += value * w;
+                    }
+                }
+            }
+        });
+    out
+}
+
+/// `GaussianPlan` に従って縮小・水平ぼかし・垂直ぼかし（元のサイズへ拡大）を行う
+///
+/// GPU 版の gaussian.wgsl と同じく、垂直のパスは縮小した解像度の入力をバイリニアで拡大しながらぼかす。
+fn gaussian_blur(source: &Image, sigma: f32) -> Image {
+    let GaussianPlan { levels, kernel } = GaussianPlan::new(sigma);
+    let mut downsampled = std::borrow::Cow::Borrowed(source);
+    for _ in 0..levels {
+        downsampled = std::borrow::Cow::Owned(halve(&downsampled));
+    }
+    let offsets = kernel_offsets(&kernel);
+    let (width, height) = (downsampled.width, downsampled.height);
+    let horizontal = filter_rows(&downsampled, &axis_taps(width, width, &offsets));
+    let vertical = filter_columns(&horizontal, &axis_taps(height, source.height, &offsets));
+    if width == source.width {
+        return vertical;
+    }
+    filter_rows(&vertical, &axis_taps(width, source.width, &[(0.0, 1.0)]))
+}
+
+/// 1/2 のサイズへの縮小（テクセルの境目をバイリニアでサンプリングした 2x2 の平均）
+fn halve(source: &Image) -> Image {
+    let (width, height) = ((source.width / 2).max(1), (source.height / 2).max(1));
+    let rows = filter_rows(source, &axis_taps(source.width, width, &[(0.0, 1.0)]));
+    filter_columns(&rows, &axis_taps(source.height, height, &[(0.0, 1.0)]))
+}
+
+/// blur.wgsl の `fs_main` と、透明で塗りつぶした出力へのアルファブレンドを合わせたもの
+fn composite(blurred: &Image, params: &Params, pixels: &mut [u8]) {
+    let (width, height) = (blurred.width as usize, blurred.height as usize);
+    let encode = srgb_encode_table();
+    // `Params::mask_at` と同じ計算。画素ごとに半径の一覧を作り直さないよう先に並べておく
+    let count = (params.hole_count as usize).min(MAX_HOLES);
+    let holes = &params.holes[..count];
+    let radii: Vec<f32> = params
+        .hole_radii
+        .iter()
+        .flatten()
+        .take(count)
+        .copied()
+        .collect();
+    pixels
+        .par_chunks_mut(width * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, out) in row.chunks_exact_mut(4).enumerate() {
+                let uv = [
+                    (x as f32 + 0.5) / width as f32,
+                    (y as f32 + 0.5) / height as f32,
+                ];
+                let mask = mask::hole_mask(uv, holes, &radii, params.resolution, params.feather);
+                if mask <= 0.0 {
+                    out.copy_from_slice(&[0; 4]);
+                    continue;
+                }
+                let [u, v] = params.effects.pixelate_uv(uv, params.resolution);
+                let color = blurred.sample(u, v);
+                let rgb = params
+                    .effects
+                    .apply([color[0], color[1], color[2]], [x as f32, y as f32]);
+                // ALPHA_BLENDING で透明に重ねるので、色はアルファ乗算済みになる
+                let alpha = (mask * params.opacity).clamp(0.0, 1.0);
+                out.copy_from_slice(&[
+                    encode_srgb(encode, rgb[0] * alpha),
+                    encode_srgb(encode, rgb[1] * alpha),
+                    encode_srgb(encode, rgb[2] * alpha),
+                    (alpha * 255.0).round() as u8,
+                ]);
+            }
+        });
+}
+
+/// sRGB の 8 ビット値から線形値への表
+fn srgb_decode_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| effects::srgb_to_linear(i as u8)))
+}
+
+/// 線形値から sRGB の 8 ビット値への表の分解能（暗部の段差が出ない程度に細かく取る）
+const ENCODE_STEPS: usize = 4096;
+
+fn srgb_encode_table() -> &'static [u8] {
+    static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..=ENCODE_STEPS)
+            .map(|i| {
+                let c = i as f32 / ENCODE_STEPS as f32;
+                let s = if c <= 0.0031308 {
+                    c * 12.92
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                };
+                (s * 255.0).round() as u8
+            })
+            .collect()
+    })
+}
+
+fn encode_srgb(table: &[u8], linear: f32) -> u8 {
+    table[(linear.clamp(0.0, 1.0) * ENCODE_STEPS as f32).round() as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::synthetic::SyntheticFrameSource;
+    use crate::capture::FrameSource;
+    use crate::effects::{Effect, EffectChain};
+    use crate::renderer::testing;
+
+    #[test]
+    fn matches_gpu_renderer() {
+        let (width, height) = (96, 64);
+        let mut source = SyntheticFrameSource::new(width, height);
+        let Some(frame) = source.next_frame(None).unwrap() else {
+            unreachable!("synthetic source always returns a frame");
+        };
+
+        let mut params = Params::new(12.0);
+        params.resolution = [width as f32, height as f32];
+        params.hole_count = 2;
+        params.holes[0] = [0.1, 0.2, 0.45, 0.8];
+        params.holes[1] = [0.6, 0.1, 0.9, 0.5];
+        params.hole_radii[0] = [8.0, 0.0, 0.0, 0.0];
+        params.feather = 4.0;
+        params.opacity = 0.9;
+        let effects = EffectChain::from_effects(
+            &[
+                Effect::Grayscale { amount: 0.5 },
+                Effect::Tint {
+                    color: [200, 120, 40, 64],
+                },
+            ],
+            1.0,
+        );
+
+        for mode in [BlurMode::Gaussian, BlurMode::DualKawase] {
+            for (strength, effects) in [(4.0, EffectChain::default()), (40.0, effects)] {
+                let mut gpu = testing::headless(width, height);
+                let mut cpu = CpuRenderer::new(width, height, 1.0);
+                let params = Params {
+                    blur_strength: strength,
+                    effects,
+                    ..params
+                };
+                for renderer in [&mut gpu as &mut dyn FrameRenderer, &mut cpu] {
+                    renderer.set_blur_mode(mode);
+                    renderer.upload_frame(&frame, (0, 0));
+                    renderer.render(&params).unwrap();
+                }
+
+                // GPU は中間テクスチャを 8 ビットに量子化する分だけずれる
+                let expected = gpu.read_output().unwrap();
+                let diffs: Vec<u8> = cpu
+                    .pixels()
+                    .iter()
+                    .zip(&expected)
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .collect();
+                let max = diffs.iter().copied().max().unwrap();
+                let mean = diffs.iter().map(|&d| f32::from(d)).sum::<f32>() / diffs.len() as f32;
+                assert!(
+                    max <= 4 && mean <= 1.0,
+                    "{:?} strength {}: max {} mean {}",
+                    mode,
+                    strength,
+                    max,
+                    mean
+                );
+            }
+        }
+    }
+
+    fn assert_close(actual: &Image, expected: &Image) {
+        assert_eq!(
+            (actual.width, actual.height),
+            (expected.width, expected.height)
+        );
+        for (i, (a, e)) in actual.pixels.iter().zip(&expected.pixels).enumerate() {
+            for c in 0..4 {
+                assert!((a[c] - e[c]).abs() < 1e-5, "pixel {i}: {a:?} != {e:?}");
+            }
+        }
+    }
+
+    /// 軸ごとの表で描いた結果が、画素ごとに `Image::sample` で読んだものと一致する
+    #[test]
+    fn separable_passes_match_bilinear_sampling() {
+        // 奇数のサイズ（縮小が 2x2 の平均からずれる）と、整数でないタップ間隔を含める
+        let (width, height) = (67, 41);
+        let source = Image::render(width, height, |u, v| {
+            [
+                (u * 37.0).fract(),
+                (v * 23.0).fract(),
+                (u * v * 11.0).fract(),
+                1.0,
+            ]
+        });
+        let kernel = GaussianKernel {
+            step: 1.5,
+            weights: vec![0.4, 0.2, 0.1],
+        };
+        let offsets = kernel_offsets(&kernel);
+        // gaussian.wgsl の 1 パス分（出力のサイズが違えば拡大しながらぼかす）
+        let reference = |source: &Image, (du, dv): (f32, f32), width: u32, height: u32| {
+            Image::render(width, height, |u, v| {
+                let mut color = source.sample(u, v).map(|c| c * kernel.weights[0]);
+                for (i, &w) in kernel.weights.iter().enumerate().skip(1) {
+                    let (ou, ov) = (du * i as f32, dv * i as f32);
+                    let (ahead, behind) =
+                        (source.sample(u + ou, v + ov), source.sample(u - ou, v - ov));
+                    for c in 0..4 {
+                        color[c] += (ahead[c] + behind[c]) * w;
+                    }
+                }
+                color
+            })
+        };
+
+        let small = halve(&source);
+        assert_close(
+            &small,
+            &Image::render(width / 2, height / 2, |u, v| source.sample(u, v)),
+        );
+
+        let horizontal = filter_rows(&source, &axis_taps(width, width, &offsets));
+        let du = kernel.step / width as f32;
+        assert_close(&horizontal, &reference(&source, (du, 0.0), width, height));
+
+        let (small_width, small_height) = (small.width, small.height);
+        let vertical = filter_columns(&small, &axis_taps(small_height, height, &offsets));
+        let upscaled = filter_rows(&vertical, &axis_taps(small_width, width, &[(0.0, 1.0)]));
+        let dv = kernel.step / small_height as f32;
+        assert_close(&upscaled, &reference(&small, (0.0, dv), width, height));
+    }
+
+    /// 幅 1 ピクセルの縦線を強くぼかした水平方向の輝度
+    fn line_profile(sigma: f32) -> Vec<f32> {
+        let (width, height) = (512, 8);
+        let mut source = Image::new(width, height);
+        for y in 0..height {
+            source.pixels[(y * width + width / 2) as usize] = [1.0; 4];
+        }
+        let blurred = gaussian_blur(&source, sigma);
+        (0..width).map(|x| blurred.get(x, height / 2)[0]).collect()
+    }
+
+    #[test]
+    fn strong_gaussian_has_no_ripples() {
+        // タップ間隔が広いと線がタップごとの縞になり、中心から単調に減らなくなる
+        let sigma = kernel::sigma_for_strength(crate::settings::MAX_BLUR_STRENGTH, 1.0);
+        let profile = line_profile(sigma);
+        // 縮小したテクセルの中心に寄るので、ピークは線の位置から縮小率の半分までずれる
+        let peak = (0..profile.len())
+            .max_by(|&a, &b| profile[a].total_cmp(&profile[b]))
+            .unwrap();
+        let levels = GaussianPlan::new(sigma).levels;
+        assert!(levels > 0);
+        assert!(
+            peak.abs_diff(profile.len() / 2) <= 1 << (levels - 1),
+            "peak at {peak}"
+        );
+        for x in peak..profile.len() - 1 {
+            assert!(profile[x + 1] <= profile[x] + 1e-6, "ripple at {x}");
+        }
+        for x in 1..=peak {
+            assert!(profile[x - 1] <= profile[x] + 1e-6, "ripple at {x}");
+        }
+        // 縮小しても総量（線 1 本分）は変わらない
+        let total: f32 = profile.iter().sum();
+        assert!((total - 1.0).abs() < 0.01, "total {total}");
+    }
+
+    #[test]
+    fn downsampled_gaussian_keeps_its_width() {
+        // 縮小してからぼかしても、縮小しない場合と同じ σ の広がりになる
+        let sigma = 40.0;
+        assert!(GaussianPlan::new(sigma).levels > 0);
+        let profile = line_profile(sigma);
+        let total: f32 = profile.iter().sum();
+        let moment = |f: &dyn Fn(f32) -> f32| -> f32 {
+            profile
+                .iter()
+                .enumerate()
+                .map(|(x, w)| f(x as f32) * w)
+                .sum::<f32>()
+                / total
+        };
+        let mean = moment(&|x| x);
+        let variance = moment(&|x| (x - mean).powi(2));
+        assert!(
+            (variance.sqrt() - sigma).abs() < sigma * 0.05,
+            "sigma {}",
+            variance.sqrt()
+        );
+    }
+}
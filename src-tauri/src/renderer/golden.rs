@@ -0,0 +1,249 @@
+//! 描画結果をチェックインした PNG（`tests/golden`）と比べるテスト
+//!
+//! 合成したキャプチャを固定の `Params` でソフトウェアのアダプターに描き、各チャンネルの差が
+//! `TOLERANCE` 以内か確かめる。Phase 2 で穴が抜けなくなったようなシェーダーの退行を拾うためのもの。
+//! シェーダーを意図して変えたときは `UPDATE_GOLDEN=1 cargo test golden` で PNG を書き直し、
+//! 差分の画像を目で確かめてからコミットする。
+//!
+//! PNG の中身は `read_output` そのまま（sRGB・アルファ乗算済み）なので、ビューアーでは暗く見える。
+
+use super::{testing, BlurMode, FrameRenderer, Params};
+use crate::capture::synthetic::SyntheticFrameSource;
+use crate::capture::FrameSource;
+use crate::effects::{Effect, EffectChain};
+use crate::geometry::{MonitorGeometry, PhysicalRect};
+use crate::window_manager::WindowInfo;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 64;
+
+/// 許容するチャンネルごとの差（アダプターによる丸めの違いの分）
+const TOLERANCE: u8 = 3;
+
+/// 1 枚分の描画条件
+struct Case {
+    name: &'static str,
+    mode: BlurMode,
+    blur_strength: f32,
+    /// 穴にするウィンドウ（物理ピクセルの矩形と角丸半径）
+    holes: &'static [(PhysicalRect, i32)],
+    feather: f32,
+    opacity: f32,
+    effects: &'static [Effect],
+}
+
+const fn rect(left: i32, top: i32, right: i32, bottom: i32) -> PhysicalRect {
+    PhysicalRect {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "gaussian_no_holes",
+        mode: BlurMode::Gaussian,
+        blur_strength: 8.0,
+        holes: &[],
+        feather: 0.0,
+        opacity: 1.0,
+        effects: &[],
+    },
+    Case {
+        name: "gaussian_rounded_hole",
+        mode: BlurMode::Gaussian,
+        blur_strength: 8.0,
+        holes: &[(rect(20, 12, 68, 48), 10)],
+        feather: 0.0,
+        opacity: 1.0,
+        effects: &[],
+    },
+    Case {
+        name: "gaussian_feathered_holes",
+        mode: BlurMode::Gaussian,
+        blur_strength: 16.0,
+        holes: &[(rect(8, 8, 40, 40), 6), (rect(48, 24, 100, 70), 0)],
+        feather: 6.0,
+        opacity: 0.75,
+        effects: &[],
+    },
+    Case {
+        name: "kawase_no_holes",
+        mode: BlurMode::DualKawase,
+        blur_strength: 12.0,
+        holes: &[],
+        feather: 0.0,
+        opacity: 1.0,
+        effects: &[],
+    },
+    Case {
+        name: "kawase_feathered_hole",
+        mode: BlurMode::DualKawase,
+        blur_strength: 12.0,
+        holes: &[(rect(24, 16, 72, 56), 8)],
+        feather: 4.0,
+        opacity: 1.0,
+        effects: &[],
+    },
+    Case {
+        name: "effects_pixelate",
+        mode: BlurMode::Gaussian,
+        blur_strength: 4.0,
+        holes: &[(rect(32, 16, 64, 48), 0)],
+        feather: 0.0,
+        opacity: 1.0,
+        effects: &[Effect::Pixelate { block_size: 8.0 }],
+    },
+    Case {
+        name: "effects_grayscale_dim_tint",
+        mode: BlurMode::Gaussian,
+        blur_strength: 8.0,
+        holes: &[(rect(32, 16, 64, 48), 12)],
+        feather: 2.0,
+        opacity: 1.0,
+        effects: &[
+            Effect::Grayscale { amount: 0.8 },
+            Effect::Dim { amount: 0.3 },
+            Effect::Tint {
+                color: [40, 80, 200, 96],
+            },
+        ],
+    },
+    // ノイズの種は描いたフレーム数から決まる。新しい Renderer の最初のフレームなので毎回同じ種になる
+    Case {
+        name: "effects_noise",
+        mode: BlurMode::Gaussian,
+        blur_strength: 8.0,
+        holes: &[(rect(32, 16, 64, 48), 0)],
+        feather: 0.0,
+        opacity: 1.0,
+        effects: &[Effect::Noise { amount: 0.5 }],
+    },
+];
+
+impl Case {
+    fn params(&self) -> Params {
+        let windows: Vec<WindowInfo> = self
+            .holes
+            .iter()
+            .enumerate()
+            .map(|(i, &(rect, corner_radius))| WindowInfo {
+                hwnd: i as isize + 1,
+                title: String::new(),
+                class_name: String::new(),
+                process_name: String::new(),
+                pid: 0,
+                owner: None,
+                styles: Vec::new(),
+                visible: true,
+                rect,
+                corner_radius,
+            })
+            .collect();
+        let monitor = MonitorGeometry::new(rect(0, 0, WIDTH as i32, HEIGHT as i32), 1.0);
+
+        let mut params = Params::new(self.blur_strength);
+        params.set_holes(&windows, &monitor);
+        params.feather = self.feather;
+        params.opacity = self.opacity;
+        params.effects = EffectChain::from_effects(self.effects, 1.0);
+        params
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name))
+}
+
+fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), Box<dyn std::error::Error>> {
+    let decoder = png::Decoder::new(File::open(path)?);
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("{} is not 8-bit RGBA", path.display()).into());
+    }
+    pixels.truncate(info.buffer_size());
+    Ok((info.width, info.height, pixels))
+}
+
+fn write_png(path: &Path, pixels: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(())
+}
+
+/// `golden` と比べて、差が `TOLERANCE` を超えたチャンネルの数と最大の差
+fn compare(actual: &[u8], golden: &[u8]) -> (usize, u8) {
+    actual
+        .iter()
+        .zip(golden)
+        .map(|(a, g)| a.abs_diff(*g))
+        .fold((0, 0), |(count, max), diff| {
+            (count + usize::from(diff > TOLERANCE), max.max(diff))
+        })
+}
+
+#[test]
+fn golden_images() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut source = SyntheticFrameSource::new(WIDTH, HEIGHT);
+    let Some(frame) = source.next_frame(None).unwrap() else {
+        unreachable!("synthetic source always returns a frame");
+    };
+
+    let mut failures = Vec::new();
+    for case in CASES {
+        // ノイズの種がフレームごとに進むので、毎回新しい Renderer で描いて種を揃える
+        let mut renderer = testing::headless(WIDTH, HEIGHT);
+        renderer.set_blur_mode(case.mode);
+        renderer.upload_frame(&frame, (0, 0));
+        renderer.render(&case.params()).unwrap();
+        let actual = renderer.read_output().unwrap();
+
+        let path = golden_path(case.name);
+        if update {
+            write_png(&path, &actual).unwrap();
+            continue;
+        }
+        let golden = match read_png(&path) {
+            Ok((WIDTH, HEIGHT, golden)) => golden,
+            Ok((width, height, _)) => {
+                failures.push(format!("{}: golden is {}x{}", case.name, width, height));
+                continue;
+            }
+            Err(e) => {
+                failures.push(format!("{}: {} ({})", case.name, e, path.display()));
+                continue;
+            }
+        };
+        let (count, max) = compare(&actual, &golden);
+        if count > 0 {
+            // 見比べられるよう、実際の出力を一時ディレクトリに残す
+            let actual_path = std::env::temp_dir().join(format!("{}.actual.png", case.name));
+            let _ = write_png(&actual_path, &actual);
+            failures.push(format!(
+                "{}: {} channels differ by more than {} (max {}), output written to {}",
+                case.name,
+                count,
+                TOLERANCE,
+                max,
+                actual_path.display()
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "golden images differ (run with UPDATE_GOLDEN=1 to regenerate):\n{}",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,450 @@
+use crate::animation::{Easing, Transition, MAX_TRANSITION_MS};
+use crate::backend::{BackdropTheme, OverlayBackend};
+use crate::effects::Effect;
+use crate::logging::LogLevel;
+use crate::renderer::BlurMode;
+use crate::window_manager::rules::{RuleSet, WindowRule};
+use crate::window_manager::HoleMode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tauri_plugin_global_shortcut::Shortcut;
+
+/// 現在の設定ファイルのスキーマバージョン
+///
+/// フィールドの意味や形が変わったら上げて、`MIGRATIONS` に変換を足すこと。
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// ぼかしの強さの上限（論理ピクセル）
+pub const MAX_BLUR_STRENGTH: f32 = 200.0;
+
+/// ぼかしの強さの既定値（論理ピクセル）
+pub const DEFAULT_BLUR_STRENGTH: f32 = 30.0;
+
+/// `MIGRATIONS[n]` はバージョン `n` の JSON を `n + 1` に変換する
+const MIGRATIONS: [fn(&mut Value); SETTINGS_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2];
+
+/// バージョン 0 は `version` を持たない初期の形式。中身は v1 と同じなのでバージョンだけ付ける
+fn migrate_v0_to_v1(value: &mut Value) {
+    if let Value::Object(map) = value {
+        map.insert("version".to_string(), Value::from(1));
+    }
+}
+
+/// v1 の `shortcuts: { "toggle_overlay": "Alt+B" }` を v2 の割り当て一覧に変える
+fn migrate_v1_to_v2(value: &mut Value) {
+    let Value::Object(map) = value else {
+        return;
+    };
+    if let Some(Value::Object(old)) = map.get("shortcuts") {
+        let bindings: Vec<Value> = old
+            .get("toggle_overlay")
+            .and_then(Value::as_str)
+            .map(|keys| serde_json::json!({ "keys": keys, "action": "toggle_overlay" }))
+            .into_iter()
+            .collect();
+        map.insert("shortcuts".to_string(), Value::Array(bindings));
+    }
+    map.insert("version".to_string(), Value::from(2));
+}
+
+/// アプリの設定（設定ディレクトリの settings.json）
+///
+/// 欠けているフィールドは既定値で補う。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub backend: OverlayBackend,
+    pub blur_mode: BlurMode,
+    /// ぼかしの強さ（論理ピクセルでのぼかし半径）
+    pub blur_strength: f32,
+    /// ぼかしの後に順に掛ける効果（キャプチャをぼかす GPU ブラー・CPU ブラーのみ。DWM の背景には効かない）
+    pub effects: Vec<Effect>,
+    /// System acrylic / System blur の背景色（RGBA。A が濃さ）
+    pub tint: [u8; 4],
+    /// System mica / System mica alt の明暗
+    pub backdrop_theme: BackdropTheme,
+    pub hole_mode: HoleMode,
+    /// フォーカスが移ったときの穴やぼかしの強さの切り替え方
+    pub transition: Transition,
+    /// オーバーレイを表示・非表示にするときのフェード
+    pub fade: Transition,
+    pub shortcuts: Vec<ShortcutBinding>,
+    /// ショートカットの `switch_profile` で切り替える見た目の組
+    pub profiles: BTreeMap<String, Profile>,
+    /// ユーザー定義のウィンドウ判定ルール（既定ルールより先に評価される）
+    pub rules: Vec<WindowRule>,
+    /// モニターごとの設定。載っていないモニターは既定値で扱う
+    pub monitors: Vec<MonitorSettings>,
+    /// ログファイルと標準出力に出す最低レベル
+    pub log_level: LogLevel,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            backend: OverlayBackend::default(),
+            blur_mode: BlurMode::default(),
+            blur_strength: DEFAULT_BLUR_STRENGTH,
+            effects: Vec::new(),
+            tint: [18, 18, 18, 200],
+            backdrop_theme: BackdropTheme::default(),
+            hole_mode: HoleMode::default(),
+            transition: Transition::default(),
+            fade: Transition {
+                duration_ms: 200,
+                easing: Easing::EaseInOut,
+            },
+            shortcuts: vec![ShortcutBinding {
+                keys: "Alt+B".to_string(),
+                action: ShortcutAction::ToggleOverlay,
+            }],
+            profiles: BTreeMap::new(),
+            rules: Vec::new(),
+            monitors: Vec::new(),
+            log_level: LogLevel::default(),
+        }
+    }
+}
+
+/// グローバルショートカットの割り当て
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutBinding {
+    /// `"Alt+B"` のような tauri-plugin-global-shortcut の書式
+    pub keys: String,
+    pub action: ShortcutAction,
+}
+
+/// ショートカットで実行する操作
+///
+/// JSON では `"toggle_overlay"` や `{ "pause": { "minutes": 10 } }` のように書く。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortcutAction {
+    /// オーバーレイの表示・非表示
+    ToggleOverlay,
+    /// ぼかしを `STRENGTH_STEP` だけ強くする
+    IncreaseStrength,
+    /// ぼかしを `STRENGTH_STEP` だけ弱くする
+    DecreaseStrength,
+    /// 効果なし → モザイク → グレースケール → 減光 → ノイズ の順に切り替える
+    CycleEffect,
+    /// アクティブウィンドウを常にぼかさないウィンドウとして登録する（登録済みなら外す）
+    PinActiveWindow,
+    /// オーバーレイを `minutes` 分だけ隠す
+    Pause { minutes: u32 },
+    /// `profiles` の見た目に切り替える
+    SwitchProfile { name: String },
+}
+
+/// ショートカットでぼかしの強さを変える幅（論理ピクセル）
+pub const STRENGTH_STEP: f32 = 5.0;
+
+/// 見た目の組。書かなかった項目は切り替え前の値のまま
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<OverlayBackend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blur_mode: Option<BlurMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blur_strength: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effects: Option<Vec<Effect>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tint: Option<[u8; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backdrop_theme: Option<BackdropTheme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hole_mode: Option<HoleMode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorSettings {
+    /// OS が返すモニター名（例: `\\.\DISPLAY1`）
+    pub name: String,
+    /// false ならこのモニターにはオーバーレイを出さない
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// 設定値の検証エラー
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// 問題のあるフィールド（例: `effects[1].amount`）
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Settings {
+    /// JSON から読み込む。古いバージョンなら移行し、値を検証する
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut value: Value = serde_json::from_str(json)?;
+        migrate(&mut value)?;
+        let settings: Settings = serde_json::from_value(value)?;
+        if let Err(errors) = settings.validate() {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(format!("Invalid settings: {}", messages.join("; ")).into());
+        }
+        Ok(settings)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json)
+    }
+
+    /// 一時ファイルに書いてから置き換える（書き込み途中で落ちても壊れた設定を残さない）
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// すべての問題を集めて返す
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        if self.version != SETTINGS_VERSION {
+            errors.push(ValidationError::new(
+                "version",
+                format!("expected {}, got {}", SETTINGS_VERSION, self.version),
+            ));
+        }
+        errors.extend(self.appearance_errors(""));
+        for (name, profile) in &self.profiles {
+            let prefix = format!("profiles.{}.", name);
+            errors.extend(self.with_profile(profile).appearance_errors(&prefix));
+        }
+        for (name, transition) in [("transition", &self.transition), ("fade", &self.fade)] {
+            if transition.duration_ms > MAX_TRANSITION_MS {
+                errors.push(ValidationError::new(
+                    format!("{}.duration_ms", name),
+                    format!("must be at most {}", MAX_TRANSITION_MS),
+                ));
+            }
+        }
+        errors.extend(self.shortcut_errors());
+
+        if let Err(e) = RuleSet::new(self.rules.clone()) {
+            errors.push(ValidationError::new("rules", e.to_string()));
+        }
+
+        for (i, monitor) in self.monitors.iter().enumerate() {
+            if monitor.name.is_empty() {
+                errors.push(ValidationError::new(
+                    format!("monitors[{}].name", i),
+                    "must not be empty",
+                ));
+            } else if self.monitors[..i].iter().any(|m| m.name == monitor.name) {
+                errors.push(ValidationError::new(
+                    format!("monitors[{}].name", i),
+                    format!("duplicate monitor {:?}", monitor.name),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// ぼかしの強さと効果の値域を調べる（プロファイルを当てた結果にも使う）
+    fn appearance_errors(&self, prefix: &str) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        if !(0.0..=MAX_BLUR_STRENGTH).contains(&self.blur_strength) {
+            errors.push(ValidationError::new(
+                format!("{}blur_strength", prefix),
+                format!("must be between 0 and {}", MAX_BLUR_STRENGTH),
+            ));
+        }
+
+        for (i, effect) in self.effects.iter().enumerate() {
+            let (name, value, range) = match *effect {
+                Effect::Pixelate { block_size } => ("block_size", block_size, 1.0..=512.0),
+                Effect::Grayscale { amount }
+                | Effect::Dim { amount }
+                | Effect::Noise { amount } => ("amount", amount, 0.0..=1.0),
+                Effect::Tint { .. } => continue,
+            };
+            if !range.contains(&value) {
+                errors.push(ValidationError::new(
+                    format!("{}effects[{}].{}", prefix, i, name),
+                    format!("must be between {} and {}", range.start(), range.end()),
+                ));
+            }
+        }
+        errors
+    }
+
+    /// 書式の誤り・同じキーの重複・存在しないプロファイルを調べる
+    fn shortcut_errors(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut parsed: Vec<(usize, Shortcut)> = Vec::new();
+        for (i, binding) in self.shortcuts.iter().enumerate() {
+            let field = format!("shortcuts[{}]", i);
+            match Shortcut::from_str(&binding.keys) {
+                Ok(shortcut) => {
+                    // "Alt+B" と "alt+b" のような書き方の違いも同じキーとして扱う
+                    if let Some((j, _)) = parsed.iter().find(|(_, s)| *s == shortcut) {
+                        errors.push(ValidationError::new(
+                            format!("{}.keys", field),
+                            format!(
+                                "{:?} conflicts with shortcuts[{}] ({:?})",
+                                binding.keys, j, self.shortcuts[*j].keys
+                            ),
+                        ));
+                    } else {
+                        parsed.push((i, shortcut));
+                    }
+                }
+                Err(e) => errors.push(ValidationError::new(
+                    format!("{}.keys", field),
+                    format!("invalid shortcut {:?}: {}", binding.keys, e),
+                )),
+            }
+
+            match &binding.action {
+                ShortcutAction::Pause { minutes: 0 } => errors.push(ValidationError::new(
+                    format!("{}.action.pause.minutes", field),
+                    "must be at least 1",
+                )),
+                ShortcutAction::SwitchProfile { name } if !self.profiles.contains_key(name) => {
+                    errors.push(ValidationError::new(
+                        format!("{}.action.switch_profile.name", field),
+                        format!("unknown profile {:?}", name),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        errors
+    }
+
+    /// `profile` を当てた設定
+    pub fn with_profile(&self, profile: &Profile) -> Settings {
+        let mut settings = self.clone();
+        if let Some(backend) = profile.backend {
+            settings.backend = backend;
+        }
+        if let Some(blur_mode) = profile.blur_mode {
+            settings.blur_mode = blur_mode;
+        }
+        if let Some(blur_strength) = profile.blur_strength {
+            settings.blur_strength = blur_strength;
+        }
+        if let Some(effects) = &profile.effects {
+            settings.effects = effects.clone();
+        }
+        if let Some(tint) = profile.tint {
+            settings.tint = tint;
+        }
+        if let Some(theme) = profile.backdrop_theme {
+            settings.backdrop_theme = theme;
+        }
+        if let Some(hole_mode) = profile.hole_mode {
+            settings.hole_mode = hole_mode;
+        }
+        settings
+    }
+
+    /// ユーザー定義ルールと既定ルールを合わせたルール集合
+    pub fn rule_set(&self) -> Result<RuleSet, Box<dyn std::error::Error>> {
+        RuleSet::new(self.rules.clone())
+    }
+}
+
+/// JSON を現在のバージョンまで順に移行する
+fn migrate(value: &mut Value) -> Result<(), Box<dyn std::error::Error>> {
+    if !value.is_object() {
+        return Err("Settings must be a JSON object".into());
+    }
+    let mut version = match value.get("version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or("version must be a non-negative integer")?,
+    };
+    if version > SETTINGS_VERSION {
+        return Err(format!(
+            "Settings version {} is newer than supported version {}",
+            version, SETTINGS_VERSION
+        )
+        .into());
+    }
+
+    while version < SETTINGS_VERSION {
+        MIGRATIONS[version as usize](value);
+        version += 1;
+        tracing::info!("Migrated settings to version {}", version);
+    }
+    Ok(())
+}
+
+/// 設定ファイルの更新を確認する間隔
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// `path` の更新を監視し、変わるたびに読み直した結果を `on_change` に渡す
+///
+/// 更新日時とサイズをポーリングで比べる（エディタの置き換え保存やシンボリックリンクでも追える）。
+/// 読み込みに失敗した場合もエラーを渡すので、呼び出し側で直前の設定を残すこと。
+pub fn watch(
+    path: PathBuf,
+    on_change: impl Fn(Result<Settings, Box<dyn std::error::Error>>) + Send + 'static,
+) {
+    let stamp = |path: &Path| {
+        std::fs::metadata(path)
+            .ok()
+            .map(|m| (m.modified().ok(), m.len()))
+    };
+    std::thread::spawn(move || {
+        let mut last = stamp(&path);
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let current = stamp(&path);
+            // 消えた（置き換え保存の途中など）ときは次に現れるまで待つ
+            if current.is_none() || current == last {
+                continue;
+            }
+            last = current;
+            on_change(Settings::load(&path));
+        }
+    });
+}
@@ -0,0 +1,164 @@
+use crate::commands::commit_settings;
+use crate::effects::Effect;
+use crate::settings::{Settings, ShortcutAction, ShortcutBinding, STRENGTH_STEP};
+use crate::window_manager::rules::{Pattern, RuleAction, WindowRule};
+use crate::OverlayState;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// 登録済みのショートカットをすべて外し、`bindings` を登録し直す
+///
+/// 書式の誤りや他のアプリとの衝突で登録できなかったものは飛ばし、理由を返す。
+pub fn register_shortcuts(app: &AppHandle, bindings: &[ShortcutBinding]) -> Vec<String> {
+    let global_shortcut = app.global_shortcut();
+    let _ = global_shortcut.unregister_all();
+
+    let mut failures = Vec::new();
+    for binding in bindings {
+        let shortcut = match binding.keys.parse::<Shortcut>() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                failures.push(format!("{:?}: {}", binding.keys, e));
+                continue;
+            }
+        };
+        let action = binding.action.clone();
+        let registered = global_shortcut.on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state() == ShortcutState::Pressed {
+                run_action(app, &action);
+            }
+        });
+        if let Err(e) = registered {
+            failures.push(format!("{:?}: {}", binding.keys, e));
+        }
+    }
+    failures
+}
+
+pub fn run_action(app: &AppHandle, action: &ShortcutAction) {
+    tracing::info!("Shortcut: {:?}", action);
+    let current = app.state::<OverlayState>().settings.lock().unwrap().clone();
+    let updated = match action {
+        ShortcutAction::ToggleOverlay => {
+            let _ = crate::toggle_overlay(app);
+            return;
+        }
+        ShortcutAction::Pause { minutes } => {
+            pause(app, Duration::from_secs(u64::from(*minutes) * 60));
+            return;
+        }
+        ShortcutAction::IncreaseStrength => adjust_strength(&current, STRENGTH_STEP),
+        ShortcutAction::DecreaseStrength => adjust_strength(&current, -STRENGTH_STEP),
+        ShortcutAction::CycleEffect => cycle_effect(&current),
+        ShortcutAction::PinActiveWindow => match pin_active_window(app, &current) {
+            Some(settings) => settings,
+            None => return,
+        },
+        ShortcutAction::SwitchProfile { name } => match current.profiles.get(name) {
+            Some(profile) => current.with_profile(profile),
+            None => {
+                tracing::warn!("Unknown profile: {}", name);
+                return;
+            }
+        },
+    };
+    if let Err(e) = commit_settings(app, updated) {
+        tracing::warn!("Shortcut {:?} failed: {:?}", action, e);
+    }
+}
+
+fn adjust_strength(settings: &Settings, delta: f32) -> Settings {
+    let mut settings = settings.clone();
+    settings.blur_strength =
+        (settings.blur_strength + delta).clamp(0.0, crate::settings::MAX_BLUR_STRENGTH);
+    settings
+}
+
+/// `CycleEffect` で順に切り替える効果
+fn effect_presets() -> Vec<Vec<Effect>> {
+    vec![
+        Vec::new(),
+        vec![Effect::Pixelate { block_size: 16.0 }],
+        vec![Effect::Grayscale { amount: 1.0 }],
+        vec![Effect::Dim { amount: 0.5 }],
+        vec![Effect::Noise { amount: 0.3 }],
+    ]
+}
+
+/// 今の効果がプリセットのどれかなら次へ、どれでもなければ先頭（効果なし）に戻す
+fn cycle_effect(settings: &Settings) -> Settings {
+    let presets = effect_presets();
+    let next = presets
+        .iter()
+        .position(|p| *p == settings.effects)
+        .map_or(0, |i| (i + 1) % presets.len());
+    let mut settings = settings.clone();
+    settings.effects = presets[next].clone();
+    settings
+}
+
+/// アクティブウィンドウと同じクラス・プロセスのウィンドウを `NeverBlur` にするルールを足す（あれば外す）
+fn pin_active_window(app: &AppHandle, settings: &Settings) -> Option<Settings> {
+    let exclude = crate::overlay_handles(app);
+    let rules = app.state::<OverlayState>().rules.lock().unwrap().clone();
+    let Some(active) = crate::window_manager::get_active_window_info(&rules, &exclude) else {
+        tracing::info!("No active window to pin");
+        return None;
+    };
+
+    let rule = WindowRule {
+        name: Some(format!("pinned: {}", active.title)),
+        class_name: Some(Pattern::Exact(active.class_name.clone())),
+        title: None,
+        process: (!active.process_name.is_empty())
+            .then(|| Pattern::Exact(active.process_name.clone())),
+        styles: Vec::new(),
+        action: RuleAction::NeverBlur,
+    };
+    let mut settings = settings.clone();
+    let pinned = settings.rules.iter().position(|r| {
+        r.action == rule.action && r.class_name == rule.class_name && r.process == rule.process
+    });
+    match pinned {
+        Some(i) => {
+            tracing::info!("Unpinned window: {}", active.title);
+            settings.rules.remove(i);
+        }
+        None => {
+            tracing::info!("Pinned window: {}", active.title);
+            // 既定ルールより先に評価されるよう先頭に置く
+            settings.rules.insert(0, rule);
+        }
+    }
+    Some(settings)
+}
+
+/// オーバーレイを隠し、`duration` 経ったら再び表示する
+///
+/// 途中で手動で表示を切り替えたり、もう一度一時停止したりした場合は、古い再開は取り消される。
+fn pause(app: &AppHandle, duration: Duration) {
+    if *app.state::<OverlayState>().visible.lock().unwrap() {
+        let _ = crate::toggle_overlay(app);
+    }
+    let until = Instant::now() + duration;
+    *app.state::<OverlayState>().paused_until.lock().unwrap() = Some(until);
+    tracing::info!("Overlay paused for {:?}", duration);
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+        let state = app.state::<OverlayState>();
+        let mut paused_until = state.paused_until.lock().unwrap();
+        if *paused_until != Some(until) {
+            return;
+        }
+        *paused_until = None;
+        drop(paused_until);
+
+        if !*state.visible.lock().unwrap() {
+            let _ = crate::toggle_overlay(&app);
+        }
+        tracing::info!("Overlay resumed");
+    });
+}
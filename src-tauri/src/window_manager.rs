@@ -1,146 +1,532 @@
-use windows::Win32::Foundation::RECT;
-use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
-use windows::Win32::UI::WindowsAndMessaging::{
-    GetClassNameW, GetForegroundWindow, GetWindowRect, GetWindowTextW, IsWindowVisible,
-};
+#[cfg(test)]
+mod fake;
+pub mod rules;
+#[cfg(windows)]
+pub mod win32;
+#[cfg(target_os = "linux")]
+pub mod x11;
+
+use crate::geometry::{hole_rect, PhysicalRect};
+use rules::{RuleAction, RuleSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct WindowInfo {
     pub hwnd: isize,
     pub title: String,
-    pub rect: Rect,
+    pub class_name: String,
+    /// 実行ファイル名（例: `notepad.exe`）。取得できなければ空
+    ///
+    /// 読むのが重いので、トラッカーは空のまま返す。必要なときに `WindowTracker::process_name` で埋める。
+    pub process_name: String,
+    pub pid: u32,
+    /// 所有者ウィンドウ（Windows の GW_OWNER / X11 の WM_TRANSIENT_FOR）
+    pub owner: Option<isize>,
+    pub styles: Vec<WindowStyle>,
+    pub visible: bool,
+    pub rect: PhysicalRect,
+    /// 角丸の半径（物理ピクセル）。角丸でなければ 0
+    pub corner_radius: i32,
 }
 
-#[derive(Debug, Clone, Copy, serde::Serialize)]
-pub struct Rect {
-    pub left: i32,
-    pub top: i32,
-    pub right: i32,
-    pub bottom: i32,
+/// ルール判定に使う OS 非依存のウィンドウスタイル
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowStyle {
+    /// ポップアップ（メニュー・ツールチップ・通知など）
+    Popup,
+    /// ツールウィンドウ（タスクバーに出ない補助ウィンドウ）
+    ToolWindow,
+    /// 常に最前面
+    Topmost,
+    /// クリックしてもアクティブにならない
+    NoActivate,
+    /// タイトルバーを持つ
+    Caption,
 }
 
-pub fn get_active_window_info(exclude_hwnd: Option<isize>) -> Option<WindowInfo> {
-    unsafe {
-        let mut hwnd = GetForegroundWindow();
-        if hwnd.is_invalid() {
-            println!("DEBUG: GetForegroundWindow returned invalid HWND");
-            return None;
+/// OS ごとのウィンドウ列挙を抽象化するトレイト
+///
+/// `rect` には OS が返す可視フレーム（Windows なら DWM の拡張フレーム境界）を入れる。
+/// 穴用の補正は `find_active_window` 側で行う。
+pub trait WindowTracker {
+    /// 現在フォアグラウンドにあるウィンドウ
+    fn foreground_window(&self) -> Option<WindowInfo>;
+
+    /// `hwnd` の一つ下（背面側）にあるウィンドウ
+    fn window_below(&self, hwnd: isize) -> Option<WindowInfo>;
+
+    /// 最前面から背面に向かう z オーダー順のウィンドウ一覧（非表示のウィンドウは含めなくてよい）
+    fn z_ordered_windows(&self) -> Vec<WindowInfo>;
+
+    /// `pid` のプロセスの実行ファイル名。取得できなければ空
+    fn process_name(&self, pid: u32) -> String;
+}
+
+/// `info` のプロセス名がまだ空なら読み込む
+fn with_process_name<T: WindowTracker + ?Sized>(tracker: &T, info: WindowInfo) -> WindowInfo {
+    if !info.process_name.is_empty() {
+        return info;
+    }
+    WindowInfo {
+        process_name: tracker.process_name(info.pid),
+        ..info
+    }
+}
+
+/// フォアグラウンド以外にどのウィンドウをぼかさずに残すか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoleMode {
+    /// フォアグラウンドのウィンドウのみ
+    #[default]
+    ForegroundOnly,
+    /// フォアグラウンドと、それが（間接的にでも）所有するウィンドウ
+    ForegroundAndOwned,
+    /// フォアグラウンドと同じプロセスのウィンドウすべて
+    SameProcess,
+}
+
+/// フォアグラウンドから背面に向かって、穴を開けるべきウィンドウを探す
+///
+/// プロセス名はルールが見るときだけ読む。
+pub fn find_active_window<T: WindowTracker + ?Sized>(
+    tracker: &T,
+    rules: &RuleSet,
+    exclude: &[isize],
+) -> Option<WindowInfo> {
+    let _span = tracing::trace_span!("tracking").entered();
+    let needs_process = rules.uses_process_name();
+    let mut current = tracker.foreground_window();
+    if current.is_none() {
+        tracing::trace!("No foreground window");
+        return None;
+    }
+
+    // 自ウィンドウを除外して背後のウィンドウを探すループ
+    while let Some(mut info) = current {
+        let is_excluded = exclude.contains(&info.hwnd);
+        if needs_process && !is_excluded && info.visible {
+            info = with_process_name(tracker, info);
         }
 
-        // 自ウィンドウを除外して背後のウィンドウを探すループ
-        loop {
-            if hwnd.is_invalid() {
-                break;
-            }
+        if is_excluded {
+            tracing::trace!("Skipping overlay window: {}", info.hwnd);
+        } else if !info.visible {
+            tracing::trace!(
+                "Skipping invisible window: '{}' ({})",
+                info.title,
+                info.class_name
+            );
+        } else if let Some(rule) = rules
+            .matching_rule(&info)
+            .filter(|r| r.action == RuleAction::Ignore)
+        {
+            tracing::trace!(
+                "Skipping window by rule {:?}: '{}' ({})",
+                rule.name,
+                info.title,
+                info.class_name
+            );
+        } else {
+            // 有効なウィンドウが見つかった
+            tracing::trace!(
+                "Found target window: '{}' ({}), hwnd: {}",
+                info.title,
+                info.class_name,
+                info.hwnd
+            );
+            return Some(WindowInfo {
+                rect: hole_rect(info.rect),
+                ..info
+            });
+        }
+
+        // 次の（下の）ウィンドウへ
+        current = tracker.window_below(info.hwnd);
+    }
+
+    tracing::trace!("No suitable background window found");
+    None
+}
 
-            let mut text: [u16; 512] = [0; 512];
-            let len = GetWindowTextW(hwnd, &mut text);
-            let title = String::from_utf16_lossy(&text[..len as usize]);
-
-            let mut class_text: [u16; 512] = [0; 512];
-            let class_len = GetClassNameW(hwnd, &mut class_text);
-            let class_name = String::from_utf16_lossy(&class_text[..class_len as usize]);
-
-            let visible = IsWindowVisible(hwnd).as_bool();
-            let is_excluded = exclude_hwnd.map_or(false, |ex| hwnd.0 as isize == ex);
-
-            // 除外すべき特殊なクラス名
-            let is_systemic = class_name == "Progman"
-                || class_name == "WorkerW"
-                || class_name == "Shell_TrayWnd"
-                || class_name == "Shell_SecondaryTrayWnd"
-                || class_name == "Windows.UI.Core.CoreWindow"
-                || class_name.contains("EdgeUiInputTopWndClass");
-
-            if is_excluded {
-                println!("DEBUG: Found current window (overlay) in foreground. Checking next...");
-            } else if is_systemic {
-                println!(
-                    "DEBUG: Skipping systemic window: '{}' ({})",
-                    title, class_name
-                );
-            } else if visible
-                && !title.is_empty()
-                && class_name != "ComboBox"
-                && class_name != "tooltips_class32"
-            {
-                // 有効なウィンドウが見つかった
-                println!(
-                    "DEBUG: Found target window: '{}' ({}), HWND: {:?}, Visible: {}",
-                    title, class_name, hwnd, visible
-                );
-                break;
+/// 穴を開けるウィンドウ一覧（先頭がフォアグラウンド）
+///
+/// `mode` に応じた関連ウィンドウに加え、`AlwaysFocus`・`NeverBlur` ルールに一致する可視ウィンドウも
+/// 含める。
+pub fn find_hole_windows<T: WindowTracker + ?Sized>(
+    tracker: &T,
+    rules: &RuleSet,
+    exclude: &[isize],
+    mode: HoleMode,
+) -> Vec<WindowInfo> {
+    let active = find_active_window(tracker, rules, exclude);
+    // 同じプロセスかどうかは pid で比べるので、プロセス名はルールが見るときだけでよい
+    let needs_process = rules.uses_process_name();
+    let candidates: Vec<WindowInfo> = tracker
+        .z_ordered_windows()
+        .into_iter()
+        .filter(|w| w.visible && !exclude.contains(&w.hwnd))
+        .filter(|w| active.as_ref().is_none_or(|a| a.hwnd != w.hwnd))
+        .map(|w| {
+            if needs_process {
+                with_process_name(tracker, w)
             } else {
-                println!(
-                    "DEBUG: Skipping window - Title: '{}' ({}), HWND: {:?}, Visible: {}, Excluded: {}",
-                    title, class_name, hwnd, visible, is_excluded
-                );
+                w
             }
+        })
+        .filter(|w| rules.action_for(w) != Some(RuleAction::Ignore))
+        .collect();
 
-            // 次の（下の）ウィンドウへ
-            hwnd = windows::Win32::UI::WindowsAndMessaging::GetWindow(
-                hwnd,
-                windows::Win32::UI::WindowsAndMessaging::GW_HWNDNEXT,
-            )
-            .unwrap_or_default();
+    // 所有関係は多段になりうるので、所有者を辿りながら広げていく
+    let mut owned: Vec<isize> = Vec::new();
+    if let (Some(active), HoleMode::ForegroundAndOwned) = (&active, mode) {
+        let mut owners = vec![active.hwnd];
+        while let Some(owner) = owners.pop() {
+            for w in &candidates {
+                if w.owner == Some(owner) && !owned.contains(&w.hwnd) {
+                    owned.push(w.hwnd);
+                    owners.push(w.hwnd);
+                }
+            }
         }
+    }
 
-        if hwnd.is_invalid() {
-            println!("DEBUG: No suitable background window found.");
-            return None;
+    let mut holes: Vec<WindowInfo> = active.into_iter().collect();
+    let active_pid = holes.first().map(|a| a.pid).filter(|&pid| pid != 0);
+    for w in candidates {
+        let keep = match mode {
+            HoleMode::ForegroundOnly => false,
+            HoleMode::ForegroundAndOwned => owned.contains(&w.hwnd),
+            HoleMode::SameProcess => active_pid == Some(w.pid),
+        } || matches!(
+            rules.action_for(&w),
+            Some(RuleAction::AlwaysFocus | RuleAction::NeverBlur)
+        );
+        if keep {
+            holes.push(WindowInfo {
+                rect: hole_rect(w.rect),
+                ..w
+            });
         }
+    }
+    holes
+}
 
-        let mut rect = RECT::default();
-        let _ = DwmGetWindowAttribute(
+#[cfg(windows)]
+fn platform_tracker() -> Option<&'static win32::Win32WindowTracker> {
+    Some(&win32::Win32WindowTracker)
+}
+
+#[cfg(target_os = "linux")]
+fn platform_tracker() -> Option<&'static x11::X11WindowTracker> {
+    // X サーバーへの接続は使い回す（接続できなければ以降も None を返す）
+    static TRACKER: OnceLock<Option<x11::X11WindowTracker>> = OnceLock::new();
+    TRACKER
+        .get_or_init(|| match x11::X11WindowTracker::connect() {
+            Ok(t) => Some(t),
+            Err(e) => {
+                tracing::error!("Failed to connect to X server: {:?}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn platform_tracker() -> Option<&'static dyn WindowTracker> {
+    None
+}
+
+/// 穴やオーバーレイの重なり順を見直すきっかけになるウィンドウの変化
+///
+/// 値は変化したウィンドウ（X11 の重なり順の変化などで特定できなければ 0）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// フォアグラウンドが切り替わった
+    Foreground(isize),
+    /// 移動・リサイズした
+    MoveResize(isize),
+    /// 表示・非表示・最小化・破棄などで重なり順が変わった
+    ZOrder(isize),
+}
+
+/// `subscribe_window_events` の購読者
+static SUBSCRIBERS: Mutex<Vec<Sender<WindowEvent>>> = Mutex::new(Vec::new());
+
+/// OS のイベントフックから呼ばれ、購読者全員に配る（受信側が無くなった購読者は外す）
+fn publish_window_event(event: WindowEvent) {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.retain(|tx| tx.send(event).is_ok());
+}
+
+#[cfg(windows)]
+fn start_event_source() -> Result<(), Box<dyn std::error::Error>> {
+    win32::start_event_hook()
+}
+
+#[cfg(target_os = "linux")]
+fn start_event_source() -> Result<(), Box<dyn std::error::Error>> {
+    x11::start_event_watcher()
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn start_event_source() -> Result<(), Box<dyn std::error::Error>> {
+    Err("window events are not supported on this platform".into())
+}
+
+/// ウィンドウの変化を受け取るチャネルを返す
+///
+/// 初回呼び出しで OS のイベントフックを起動する。フックが使えない環境では `None` を返すので、
+/// 呼び出し側はポーリングに戻ること。
+pub fn subscribe_window_events() -> Option<Receiver<WindowEvent>> {
+    static STARTED: OnceLock<bool> = OnceLock::new();
+    let started = *STARTED.get_or_init(|| match start_event_source() {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(
+                "Window event hook unavailable, falling back to polling: {}",
+                e
+            );
+            false
+        }
+    });
+    if !started {
+        return None;
+    }
+
+    let (tx, rx) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(tx);
+    Some(rx)
+}
+
+/// ウィンドウの変化を待つ。`events` が無ければ `interval` だけ眠る（ポーリング）
+///
+/// 変化が続けて届いた場合はまとめて 1 回として扱う。`interval` 内に何も届かなくても戻るので、
+/// 取りこぼしがあっても次の呼び出しで追いつける。
+pub fn wait_for_window_change(events: Option<&Receiver<WindowEvent>>, interval: Duration) {
+    match events {
+        Some(rx) => {
+            if rx.recv_timeout(interval).is_ok() {
+                while rx.try_recv().is_ok() {}
+            }
+        }
+        None => std::thread::sleep(interval),
+    }
+}
+
+/// 穴を開けるウィンドウ（プロセス名も埋める）
+pub fn get_active_window_info(rules: &RuleSet, exclude: &[isize]) -> Option<WindowInfo> {
+    let tracker = platform_tracker()?;
+    find_active_window(tracker, rules, exclude).map(|info| with_process_name(tracker, info))
+}
+
+/// 表示中のウィンドウを最前面から順に返す（ルールによる除外はしない）
+pub fn list_windows() -> Vec<WindowInfo> {
+    match platform_tracker() {
+        Some(tracker) => tracker
+            .z_ordered_windows()
+            .into_iter()
+            .filter(|w| w.visible)
+            .map(|w| with_process_name(tracker, w))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn get_hole_windows(rules: &RuleSet, exclude: &[isize], mode: HoleMode) -> Vec<WindowInfo> {
+    match platform_tracker() {
+        Some(tracker) => find_hole_windows(tracker, rules, exclude, mode),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeWindowTracker;
+    use super::*;
+    use rules::{Pattern, WindowRule};
+
+    fn rect(i: i32) -> PhysicalRect {
+        PhysicalRect {
+            left: i * 10,
+            top: i * 10,
+            right: i * 10 + 100,
+            bottom: i * 10 + 80,
+        }
+    }
+
+    fn window(hwnd: isize, pid: u32, owner: Option<isize>) -> WindowInfo {
+        WindowInfo {
             hwnd,
-            DWMWA_EXTENDED_FRAME_BOUNDS,
-            &mut rect as *mut _ as *mut _,
-            std::mem::size_of::<RECT>() as u32,
-        );
+            title: format!("window {}", hwnd),
+            class_name: "App".to_string(),
+            process_name: String::new(),
+            pid,
+            owner,
+            styles: Vec::new(),
+            visible: true,
+            rect: rect(hwnd as i32),
+            corner_radius: 0,
+        }
+    }
 
-        let mut win_rect = RECT::default();
-        let _ = GetWindowRect(hwnd, &mut win_rect);
-
-        let mut text: [u16; 512] = [0; 512];
-        let len = GetWindowTextW(hwnd, &mut text);
-        let title = String::from_utf16_lossy(&text[..len as usize]);
-
-        println!("DEBUG: Boundary Comparison for '{}':", title);
-        println!(
-            "  DWM (Visible): L:{}, T:{}, R:{}, B:{} ({}x{})",
-            rect.left,
-            rect.top,
-            rect.right,
-            rect.bottom,
-            rect.right - rect.left,
-            rect.bottom - rect.top
-        );
-        println!(
-            "  Win32 (Full): L:{}, T:{}, R:{}, B:{} ({}x{})",
-            win_rect.left,
-            win_rect.top,
-            win_rect.right,
-            win_rect.bottom,
-            win_rect.right - win_rect.left,
-            win_rect.bottom - win_rect.top
+    /// 1 が前面。2 は 1 の所有、3 は 2 の所有、4 は 1 と同じプロセス、5 は無関係
+    fn tracker() -> FakeWindowTracker {
+        let mut tracker = FakeWindowTracker::new();
+        tracker
+            .push(window(1, 100, None))
+            .push(window(2, 100, Some(1)))
+            .push(window(3, 200, Some(2)))
+            .push(window(4, 100, None))
+            .push(window(5, 300, None))
+            .set_foreground(Some(1));
+        tracker
+    }
+
+    fn hwnds(windows: &[WindowInfo]) -> Vec<isize> {
+        windows.iter().map(|w| w.hwnd).collect()
+    }
+
+    fn class_rule(class: &str, action: RuleAction) -> WindowRule {
+        WindowRule {
+            name: None,
+            class_name: Some(Pattern::Exact(class.to_string())),
+            title: None,
+            process: None,
+            styles: Vec::new(),
+            action,
+        }
+    }
+
+    #[test]
+    fn active_window_is_foreground_with_hole_margin() {
+        let active = find_active_window(&tracker(), &RuleSet::default(), &[]).unwrap();
+        assert_eq!(active.hwnd, 1);
+        assert_eq!(active.rect, hole_rect(rect(1)));
+        assert_eq!(active.rect, rect(1).inflate(1));
+    }
+
+    #[test]
+    fn active_window_skips_excluded_invisible_and_ignored() {
+        let mut tracker = FakeWindowTracker::new();
+        let mut hidden = window(2, 0, None);
+        hidden.visible = false;
+        let mut desktop = window(3, 0, None);
+        desktop.class_name = "Progman".to_string();
+        let mut untitled = window(4, 0, None);
+        untitled.title.clear();
+        tracker
+            .push(window(1, 0, None))
+            .push(hidden)
+            .push(desktop)
+            .push(untitled)
+            .push(window(5, 0, None))
+            .set_foreground(Some(1));
+
+        let active = find_active_window(&tracker, &RuleSet::default(), &[1]);
+        assert_eq!(active.map(|w| w.hwnd), Some(5));
+    }
+
+    #[test]
+    fn no_active_window_without_foreground() {
+        let mut tracker = tracker();
+        tracker.set_foreground(None);
+        assert!(find_active_window(&tracker, &RuleSet::default(), &[]).is_none());
+        let holes = find_hole_windows(&tracker, &RuleSet::default(), &[], HoleMode::SameProcess);
+        assert!(holes.is_empty());
+    }
+
+    #[test]
+    fn foreground_only_keeps_just_the_foreground() {
+        let holes = find_hole_windows(
+            &tracker(),
+            &RuleSet::default(),
+            &[],
+            HoleMode::ForegroundOnly,
         );
-        println!(
-            "  Offset (DWM - Win32): L:{}, T:{}, R:{}, B:{}",
-            rect.left - win_rect.left,
-            rect.top - win_rect.top,
-            rect.right - win_rect.right,
-            rect.bottom - win_rect.bottom
+        assert_eq!(hwnds(&holes), [1]);
+    }
+
+    #[test]
+    fn foreground_and_owned_follows_ownership_chain() {
+        let holes = find_hole_windows(
+            &tracker(),
+            &RuleSet::default(),
+            &[],
+            HoleMode::ForegroundAndOwned,
         );
+        assert_eq!(hwnds(&holes), [1, 2, 3]);
+        assert!(holes
+            .iter()
+            .all(|w| w.rect == hole_rect(rect(w.hwnd as i32))));
+    }
 
-        Some(WindowInfo {
-            hwnd: hwnd.0 as isize,
-            title,
-            rect: Rect {
-                left: rect.left - 1,
-                top: rect.top - 1,
-                right: rect.right + 1,
-                bottom: rect.bottom + 1,
-            },
-        })
+    #[test]
+    fn same_process_keeps_windows_with_foreground_pid() {
+        let holes = find_hole_windows(&tracker(), &RuleSet::default(), &[], HoleMode::SameProcess);
+        assert_eq!(hwnds(&holes), [1, 2, 4]);
+    }
+
+    #[test]
+    fn same_process_ignores_unknown_pid() {
+        let mut tracker = FakeWindowTracker::new();
+        tracker
+            .push(window(1, 0, None))
+            .push(window(2, 0, None))
+            .set_foreground(Some(1));
+        let holes = find_hole_windows(&tracker, &RuleSet::default(), &[], HoleMode::SameProcess);
+        assert_eq!(hwnds(&holes), [1]);
+    }
+
+    #[test]
+    fn never_blur_windows_are_holes_in_every_mode() {
+        let mut tracker = tracker();
+        let mut pinned = window(6, 400, None);
+        pinned.class_name = "Pinned".to_string();
+        tracker.push(pinned);
+        let rules = RuleSet::new(vec![class_rule("Pinned", RuleAction::NeverBlur)]).unwrap();
+
+        for mode in [
+            HoleMode::ForegroundOnly,
+            HoleMode::ForegroundAndOwned,
+            HoleMode::SameProcess,
+        ] {
+            let holes = find_hole_windows(&tracker, &rules, &[], mode);
+            assert_eq!(holes.first().map(|w| w.hwnd), Some(1), "{:?}", mode);
+            assert!(hwnds(&holes).contains(&6), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn always_focus_window_is_kept_alongside_foreground() {
+        let mut tracker = tracker();
+        tracker.windows[4].class_name = "Player".to_string();
+        let rules = RuleSet::new(vec![class_rule("Player", RuleAction::AlwaysFocus)]).unwrap();
+
+        // フォアグラウンドはそのまま
+        assert_eq!(find_active_window(&tracker, &rules, &[]).unwrap().hwnd, 1);
+        let holes = find_hole_windows(&tracker, &rules, &[], HoleMode::ForegroundOnly);
+        assert_eq!(hwnds(&holes), [1, 5]);
+        assert_eq!(holes[1].rect, hole_rect(rect(5)));
+
+        // 隠れている・除外されているなら穴にしない
+        tracker.windows[4].visible = false;
+        let holes = find_hole_windows(&tracker, &rules, &[], HoleMode::ForegroundOnly);
+        assert_eq!(hwnds(&holes), [1]);
+        tracker.windows[4].visible = true;
+        let holes = find_hole_windows(&tracker, &rules, &[5], HoleMode::ForegroundOnly);
+        assert_eq!(hwnds(&holes), [1]);
+    }
+
+    #[test]
+    fn hole_windows_skip_excluded_and_ignored() {
+        let mut tracker = tracker();
+        tracker.windows[3].class_name = "Ignored".to_string();
+        let rules = RuleSet::new(vec![class_rule("Ignored", RuleAction::Ignore)]).unwrap();
+        let holes = find_hole_windows(&tracker, &rules, &[2], HoleMode::SameProcess);
+        assert_eq!(hwnds(&holes), [1]);
     }
 }
@@ -0,0 +1,55 @@
+use super::{WindowInfo, WindowTracker};
+
+/// テスト用のインメモリ実装
+///
+/// `windows` は最前面から背面に向かう z オーダー順で保持する。
+#[derive(Debug, Clone, Default)]
+pub struct FakeWindowTracker {
+    pub windows: Vec<WindowInfo>,
+    pub foreground: Option<isize>,
+}
+
+impl FakeWindowTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 最背面にウィンドウを追加する
+    pub fn push(&mut self, info: WindowInfo) -> &mut Self {
+        self.windows.push(info);
+        self
+    }
+
+    pub fn set_foreground(&mut self, hwnd: Option<isize>) -> &mut Self {
+        self.foreground = hwnd;
+        self
+    }
+
+    fn position(&self, hwnd: isize) -> Option<usize> {
+        self.windows.iter().position(|w| w.hwnd == hwnd)
+    }
+}
+
+impl WindowTracker for FakeWindowTracker {
+    fn foreground_window(&self) -> Option<WindowInfo> {
+        let hwnd = self.foreground?;
+        self.position(hwnd).map(|i| self.windows[i].clone())
+    }
+
+    fn window_below(&self, hwnd: isize) -> Option<WindowInfo> {
+        let i = self.position(hwnd)?;
+        self.windows.get(i + 1).cloned()
+    }
+
+    fn z_ordered_windows(&self) -> Vec<WindowInfo> {
+        self.windows.clone()
+    }
+
+    fn process_name(&self, pid: u32) -> String {
+        self.windows
+            .iter()
+            .find(|w| w.pid == pid)
+            .map(|w| w.process_name.clone())
+            .unwrap_or_default()
+    }
+}
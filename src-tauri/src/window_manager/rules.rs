@@ -0,0 +1,375 @@
+use super::{WindowInfo, WindowStyle};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// 文字列のマッチ方法
+///
+/// JSON では `"Progman"`（完全一致）、`{ "glob": "*Popup*" }`、`{ "regex": "^Tool.*$" }` のいずれかで書く。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Pattern {
+    Exact(String),
+    Glob { glob: String },
+    Regex { regex: String },
+}
+
+/// ルールに一致したウィンドウの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    /// 穴の対象にせず、背面のウィンドウを探し続ける
+    Ignore,
+    /// フォアグラウンドでなくても、フォアグラウンドと並べて穴の対象にする
+    AlwaysFocus,
+    /// フォーカスに関係なく常にぼかさない
+    NeverBlur,
+}
+
+/// 宣言的なウィンドウ判定ルール
+///
+/// 指定された条件をすべて満たしたときに一致する。条件を一つも持たないルールは無効。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<Pattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Pattern>,
+    /// 実行ファイル名（例: `notepad.exe`）。大文字小文字は区別しない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<Pattern>,
+    /// すべて持っていれば一致するスタイル
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub styles: Vec<WindowStyle>,
+    pub action: RuleAction,
+}
+
+impl WindowRule {
+    fn class(pattern: Pattern, action: RuleAction) -> Self {
+        Self {
+            name: None,
+            class_name: Some(pattern),
+            title: None,
+            process: None,
+            styles: Vec::new(),
+            action,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("{:?}", self),
+        }
+    }
+}
+
+enum Matcher {
+    Exact(String),
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn compile(pattern: &Pattern, ignore_case: bool) -> Result<Self, regex::Error> {
+        let fold = |s: &str| {
+            if ignore_case {
+                s.to_lowercase()
+            } else {
+                s.to_string()
+            }
+        };
+        Ok(match pattern {
+            Pattern::Exact(s) => Matcher::Exact(fold(s)),
+            Pattern::Glob { glob } => Matcher::Glob(fold(glob)),
+            Pattern::Regex { regex } => Matcher::Regex(
+                regex::RegexBuilder::new(regex)
+                    .case_insensitive(ignore_case)
+                    .build()?,
+            ),
+        })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Exact(s) => s == text,
+            Matcher::Glob(g) => glob_match(g, text),
+            Matcher::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+/// `*`（0 文字以上）と `?`（1 文字）だけをサポートする簡易 glob
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // 最後に見た `*` の位置と、そこから再開するテキスト位置
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            backtrack = Some((pi, ti));
+            pi += 1;
+        } else if let Some((star, resume)) = backtrack {
+            pi = star + 1;
+            ti = resume + 1;
+            backtrack = Some((star, resume + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+struct CompiledRule {
+    rule: WindowRule,
+    class_name: Option<Matcher>,
+    title: Option<Matcher>,
+    process: Option<Matcher>,
+}
+
+impl CompiledRule {
+    fn compile(rule: WindowRule) -> Result<Self, Box<dyn std::error::Error>> {
+        if rule.class_name.is_none()
+            && rule.title.is_none()
+            && rule.process.is_none()
+            && rule.styles.is_empty()
+        {
+            return Err(format!("Rule '{}' has no conditions", rule.describe()).into());
+        }
+
+        let compile = |pattern: &Option<Pattern>, ignore_case: bool| {
+            pattern
+                .as_ref()
+                .map(|p| Matcher::compile(p, ignore_case))
+                .transpose()
+                .map_err(|e| format!("Rule '{}': invalid regex: {}", rule.describe(), e))
+        };
+        let class_name = compile(&rule.class_name, false)?;
+        let title = compile(&rule.title, false)?;
+        let process = compile(&rule.process, true)?;
+
+        Ok(Self {
+            rule,
+            class_name,
+            title,
+            process,
+        })
+    }
+
+    fn is_match(&self, info: &WindowInfo) -> bool {
+        self.class_name
+            .as_ref()
+            .is_none_or(|m| m.is_match(&info.class_name))
+            && self.title.as_ref().is_none_or(|m| m.is_match(&info.title))
+            && self
+                .process
+                .as_ref()
+                .is_none_or(|m| m.is_match(&info.process_name.to_lowercase()))
+            && self.rule.styles.iter().all(|s| info.styles.contains(s))
+    }
+}
+
+/// 順番に評価され、最初に一致したルールが採用されるルール集合
+///
+/// ユーザー定義のルールは既定ルール（デスクトップ・タスクバー・ツールチップ等の除外）より先に評価される。
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// ユーザー定義のルールの後ろに既定ルールを連結して構築する
+    pub fn new(user_rules: Vec<WindowRule>) -> Result<Self, Box<dyn std::error::Error>> {
+        let rules = user_rules
+            .into_iter()
+            .chain(default_rules())
+            .map(CompiledRule::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// JSON のルール配列から構築する
+    #[cfg(test)]
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let user_rules: Vec<WindowRule> = serde_json::from_str(json)?;
+        Self::new(user_rules)
+    }
+
+    /// 最初に一致したルールの動作。一致しなければ `None`
+    pub fn action_for(&self, info: &WindowInfo) -> Option<RuleAction> {
+        self.matching_rule(info).map(|r| r.action)
+    }
+
+    /// プロセス名で絞り込むルールを持つか（持たなければプロセス名を読まずに済む）
+    pub fn uses_process_name(&self) -> bool {
+        self.rules.iter().any(|r| r.process.is_some())
+    }
+
+    pub fn matching_rule(&self, info: &WindowInfo) -> Option<&WindowRule> {
+        self.rules
+            .iter()
+            .find(|r| r.is_match(info))
+            .map(|r| &r.rule)
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::new(Vec::new()).expect("default rules must compile")
+    }
+}
+
+/// 従来ハードコードされていた除外条件
+pub fn default_rules() -> Vec<WindowRule> {
+    let ignore_class =
+        |class: &str| WindowRule::class(Pattern::Exact(class.to_string()), RuleAction::Ignore);
+    vec![
+        // デスクトップ・タスクバー等のシステムウィンドウ
+        ignore_class("Progman"),
+        ignore_class("WorkerW"),
+        ignore_class("Shell_TrayWnd"),
+        ignore_class("Shell_SecondaryTrayWnd"),
+        ignore_class("Windows.UI.Core.CoreWindow"),
+        WindowRule::class(
+            Pattern::Glob {
+                glob: "*EdgeUiInputTopWndClass*".to_string(),
+            },
+            RuleAction::Ignore,
+        ),
+        // ドロップダウンやツールチップ
+        ignore_class("ComboBox"),
+        ignore_class("tooltips_class32"),
+        // タイトルの無いウィンドウ
+        WindowRule {
+            name: Some("untitled".to_string()),
+            class_name: None,
+            title: Some(Pattern::Exact(String::new())),
+            process: None,
+            styles: Vec::new(),
+            action: RuleAction::Ignore,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::PhysicalRect;
+
+    fn window(title: &str, class_name: &str, process_name: &str) -> WindowInfo {
+        WindowInfo {
+            hwnd: 1,
+            title: title.to_string(),
+            class_name: class_name.to_string(),
+            process_name: process_name.to_string(),
+            pid: 1,
+            owner: None,
+            styles: Vec::new(),
+            visible: true,
+            rect: PhysicalRect::default(),
+            corner_radius: 0,
+        }
+    }
+
+    fn rules(json: &str) -> RuleSet {
+        RuleSet::from_json(json).unwrap()
+    }
+
+    #[test]
+    fn exact_pattern_matches_whole_string() {
+        let set = rules(r#"[{ "class_name": "Chrome_WidgetWin_1", "action": "never_blur" }]"#);
+        let action = |class: &str| set.action_for(&window("t", class, ""));
+        assert_eq!(action("Chrome_WidgetWin_1"), Some(RuleAction::NeverBlur));
+        assert_eq!(action("Chrome_WidgetWin_10"), None);
+        assert_eq!(action("chrome_widgetwin_1"), None);
+    }
+
+    #[test]
+    fn glob_pattern() {
+        assert!(glob_match("*Popup*", "MozillaPopupWindow"));
+        assert!(glob_match("Tool?", "Tool1"));
+        assert!(!glob_match("Tool?", "Tool12"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(!glob_match("a*b*c", "aXXbYY"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+
+        let set = rules(r#"[{ "title": { "glob": "* - Zoom" }, "action": "ignore" }]"#);
+        assert_eq!(
+            set.action_for(&window("Meeting - Zoom", "X", "")),
+            Some(RuleAction::Ignore)
+        );
+        assert_eq!(set.action_for(&window("Zoom", "X", "")), None);
+    }
+
+    #[test]
+    fn regex_pattern() {
+        let set = rules(r#"[{ "class_name": { "regex": "^Tool\\d+$" }, "action": "ignore" }]"#);
+        assert_eq!(
+            set.action_for(&window("t", "Tool42", "")),
+            Some(RuleAction::Ignore)
+        );
+        assert_eq!(set.action_for(&window("t", "Tool42x", "")), None);
+        assert!(
+            RuleSet::from_json(r#"[{ "title": { "regex": "(" }, "action": "ignore" }]"#).is_err()
+        );
+    }
+
+    #[test]
+    fn process_name_ignores_case() {
+        let set = rules(r#"[{ "process": "Notepad.EXE", "action": "always_focus" }]"#);
+        assert_eq!(
+            set.action_for(&window("t", "X", "notepad.exe")),
+            Some(RuleAction::AlwaysFocus)
+        );
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let set = rules(
+            r#"[{ "class_name": "X", "title": { "glob": "Save*" }, "styles": ["popup"], "action": "ignore" }]"#,
+        );
+        let mut info = window("Save as", "X", "");
+        assert_eq!(set.action_for(&info), None);
+        info.styles.push(WindowStyle::Popup);
+        assert_eq!(set.action_for(&info), Some(RuleAction::Ignore));
+        info.title = "Open".to_string();
+        assert_eq!(set.action_for(&info), None);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let set = rules(
+            r#"[
+                { "name": "first", "class_name": { "glob": "App*" }, "action": "never_blur" },
+                { "name": "second", "class_name": "AppMain", "action": "ignore" }
+            ]"#,
+        );
+        let rule = set.matching_rule(&window("t", "AppMain", "")).unwrap();
+        assert_eq!(rule.name.as_deref(), Some("first"));
+        assert_eq!(rule.action, RuleAction::NeverBlur);
+    }
+
+    #[test]
+    fn user_rules_take_precedence_over_defaults() {
+        assert_eq!(
+            RuleSet::default().action_for(&window("", "Progman", "")),
+            Some(RuleAction::Ignore)
+        );
+        let set = rules(r#"[{ "class_name": "Progman", "action": "never_blur" }]"#);
+        assert_eq!(
+            set.action_for(&window("", "Progman", "")),
+            Some(RuleAction::NeverBlur)
+        );
+    }
+
+    #[test]
+    fn rule_without_conditions_is_rejected() {
+        assert!(RuleSet::from_json(r#"[{ "name": "empty", "action": "ignore" }]"#).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use super::{WindowEvent, WindowInfo, WindowStyle, WindowTracker};
+use crate::geometry::{self, PhysicalRect};
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{CloseHandle, HWND, RECT};
+use windows::Win32::Graphics::Dwm::{
+    DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS, DWMWA_WINDOW_CORNER_PREFERENCE,
+    DWMWCP_DONOTROUND, DWMWCP_ROUNDSMALL, DWM_WINDOW_CORNER_PREFERENCE,
+};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK};
+use windows::Win32::UI::HiDpi::GetDpiForWindow;
+use windows::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetAncestor, GetClassNameW, GetForegroundWindow, GetMessageW, GetTopWindow,
+    GetWindow, GetWindowLongW, GetWindowRect, GetWindowTextW, GetWindowThreadProcessId,
+    IsWindowVisible, IsZoomed, TranslateMessage, CHILDID_SELF, EVENT_OBJECT_DESTROY,
+    EVENT_OBJECT_HIDE, EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_REORDER, EVENT_SYSTEM_FOREGROUND,
+    EVENT_SYSTEM_MINIMIZEEND, EVENT_SYSTEM_MINIMIZESTART, EVENT_SYSTEM_MOVESIZEEND, GA_ROOT,
+    GWL_EXSTYLE, GWL_STYLE, GW_HWNDNEXT, GW_OWNER, MSG, OBJID_WINDOW, WINEVENT_OUTOFCONTEXT,
+    WINEVENT_SKIPOWNPROCESS, WS_CAPTION, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    WS_POPUP,
+};
+
+fn physical_rect(rect: RECT) -> PhysicalRect {
+    PhysicalRect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    }
+}
+
+/// Win32 API (GetForegroundWindow / GetWindow / DWM) による実装
+pub struct Win32WindowTracker;
+
+/// ウィンドウを所有するプロセスの実行ファイル名
+unsafe fn process_name(pid: u32) -> String {
+    if pid == 0 {
+        return String::new();
+    }
+
+    let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+        return String::new();
+    };
+    let mut path: [u16; 1024] = [0; 1024];
+    let mut len = path.len() as u32;
+    let result = QueryFullProcessImageNameW(
+        process,
+        PROCESS_NAME_WIN32,
+        PWSTR(path.as_mut_ptr()),
+        &mut len,
+    );
+    let _ = CloseHandle(process);
+    if result.is_err() {
+        return String::new();
+    }
+
+    let path = String::from_utf16_lossy(&path[..len as usize]);
+    path.rsplit('\\').next().unwrap_or_default().to_string()
+}
+
+/// GWL_STYLE / GWL_EXSTYLE を OS 非依存のスタイルに変換する
+unsafe fn window_styles(hwnd: HWND) -> Vec<WindowStyle> {
+    let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+    let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+
+    let mut styles = Vec::new();
+    if style & WS_POPUP.0 != 0 {
+        styles.push(WindowStyle::Popup);
+    }
+    if style & WS_CAPTION.0 == WS_CAPTION.0 {
+        styles.push(WindowStyle::Caption);
+    }
+    if ex_style & WS_EX_TOOLWINDOW.0 != 0 {
+        styles.push(WindowStyle::ToolWindow);
+    }
+    if ex_style & WS_EX_TOPMOST.0 != 0 {
+        styles.push(WindowStyle::Topmost);
+    }
+    if ex_style & WS_EX_NOACTIVATE.0 != 0 {
+        styles.push(WindowStyle::NoActivate);
+    }
+    styles
+}
+
+/// Windows 11 の角丸半径（96 DPI 時の論理ピクセル）
+const ROUND_RADIUS: i32 = 8;
+const ROUND_SMALL_RADIUS: i32 = 4;
+
+/// DWM が描く角丸の半径（物理ピクセル）
+///
+/// DWMWA_WINDOW_CORNER_PREFERENCE を知らない Windows 10 以前では取得に失敗するので 0 を返す。
+unsafe fn corner_radius(hwnd: HWND, styles: &[WindowStyle]) -> i32 {
+    let mut preference = DWM_WINDOW_CORNER_PREFERENCE::default();
+    let result = DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_WINDOW_CORNER_PREFERENCE,
+        &mut preference as *mut _ as *mut _,
+        std::mem::size_of::<DWM_WINDOW_CORNER_PREFERENCE>() as u32,
+    );
+    // 最大化したウィンドウは角が丸められない
+    if result.is_err() || IsZoomed(hwnd).as_bool() {
+        return 0;
+    }
+
+    let radius = match preference {
+        DWMWCP_DONOTROUND => return 0,
+        DWMWCP_ROUNDSMALL => ROUND_SMALL_RADIUS,
+        // 既定ではポップアップ（メニュー等）は小さい角丸になる
+        _ if styles.contains(&WindowStyle::Popup) => ROUND_SMALL_RADIUS,
+        _ => ROUND_RADIUS,
+    };
+    let dpi = GetDpiForWindow(hwnd).max(96) as i32;
+    radius * dpi / 96
+}
+
+/// HWND からタイトル・クラス名・可視フレームを読み取る
+unsafe fn read_window(hwnd: HWND) -> WindowInfo {
+    let mut text: [u16; 512] = [0; 512];
+    let len = GetWindowTextW(hwnd, &mut text);
+    let title = String::from_utf16_lossy(&text[..len as usize]);
+
+    let mut class_text: [u16; 512] = [0; 512];
+    let class_len = GetClassNameW(hwnd, &mut class_text);
+    let class_name = String::from_utf16_lossy(&class_text[..class_len as usize]);
+
+    let visible = IsWindowVisible(hwnd).as_bool();
+
+    let mut pid = 0u32;
+    GetWindowThreadProcessId(hwnd, Some(&mut pid));
+    let owner = GetWindow(hwnd, GW_OWNER)
+        .ok()
+        .filter(|o| !o.is_invalid())
+        .map(|o| o.0 as isize);
+
+    // DWM の拡張フレーム境界は不可視の境界線を含まない
+    let mut frame = RECT::default();
+    let extended_frame = DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_EXTENDED_FRAME_BOUNDS,
+        &mut frame as *mut _ as *mut _,
+        std::mem::size_of::<RECT>() as u32,
+    )
+    .ok()
+    .map(|_| physical_rect(frame));
+    let mut window_rect = RECT::default();
+    let _ = GetWindowRect(hwnd, &mut window_rect);
+    let rect = geometry::visible_frame(
+        physical_rect(window_rect),
+        extended_frame,
+        GetDpiForWindow(hwnd),
+    );
+
+    let styles = window_styles(hwnd);
+    let corner_radius = corner_radius(hwnd, &styles);
+
+    WindowInfo {
+        hwnd: hwnd.0 as isize,
+        title,
+        class_name,
+        process_name: String::new(),
+        pid,
+        owner,
+        styles,
+        visible,
+        rect,
+        corner_radius,
+    }
+}
+
+impl WindowTracker for Win32WindowTracker {
+    fn foreground_window(&self) -> Option<WindowInfo> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.is_invalid() {
+                return None;
+            }
+            Some(read_window(hwnd))
+        }
+    }
+
+    fn window_below(&self, hwnd: isize) -> Option<WindowInfo> {
+        unsafe {
+            let next = GetWindow(HWND(hwnd as *mut _), GW_HWNDNEXT).unwrap_or_default();
+            if next.is_invalid() {
+                return None;
+            }
+            Some(read_window(next))
+        }
+    }
+
+    fn z_ordered_windows(&self) -> Vec<WindowInfo> {
+        let mut windows = Vec::new();
+        unsafe {
+            let mut hwnd = GetTopWindow(None).unwrap_or_default();
+            while !hwnd.is_invalid() {
+                // 非表示のウィンドウは数が多く、読むだけ無駄なので飛ばす
+                if IsWindowVisible(hwnd).as_bool() {
+                    windows.push(read_window(hwnd));
+                }
+                hwnd = GetWindow(hwnd, GW_HWNDNEXT).unwrap_or_default();
+            }
+        }
+        windows
+    }
+
+    fn process_name(&self, pid: u32) -> String {
+        unsafe { process_name(pid) }
+    }
+}
+
+/// `SetWinEventHook` のコールバック。フックを登録したスレッドのメッセージループから呼ばれる
+unsafe extern "system" fn win_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    // キャレットやカーソル、子ウィンドウの変化は無視してトップレベルだけを見る
+    if hwnd.is_invalid()
+        || id_object != OBJID_WINDOW.0
+        || id_child != CHILDID_SELF as i32
+        || GetAncestor(hwnd, GA_ROOT) != hwnd
+    {
+        return;
+    }
+
+    let hwnd = hwnd.0 as isize;
+    let event = match event {
+        EVENT_SYSTEM_FOREGROUND => WindowEvent::Foreground(hwnd),
+        EVENT_OBJECT_LOCATIONCHANGE | EVENT_SYSTEM_MOVESIZEEND => WindowEvent::MoveResize(hwnd),
+        _ => WindowEvent::ZOrder(hwnd),
+    };
+    super::publish_window_event(event);
+}
+
+/// フォーカス・移動/リサイズ・表示状態の変化を購読するスレッドを起動する
+///
+/// `WINEVENT_OUTOFCONTEXT` のフックは登録したスレッドでメッセージを回す必要があるので、専用スレッドを立てる。
+pub fn start_event_hook() -> Result<(), Box<dyn std::error::Error>> {
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || unsafe {
+        let ranges = [
+            (EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND),
+            (EVENT_SYSTEM_MOVESIZEEND, EVENT_SYSTEM_MOVESIZEEND),
+            (EVENT_SYSTEM_MINIMIZESTART, EVENT_SYSTEM_MINIMIZEEND),
+            (EVENT_OBJECT_DESTROY, EVENT_OBJECT_HIDE),
+            (EVENT_OBJECT_REORDER, EVENT_OBJECT_REORDER),
+            (EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_LOCATIONCHANGE),
+        ];
+        let hooks: Vec<HWINEVENTHOOK> = ranges
+            .iter()
+            .map(|&(min, max)| {
+                SetWinEventHook(
+                    min,
+                    max,
+                    None,
+                    Some(win_event_proc),
+                    0,
+                    0,
+                    WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+                )
+            })
+            .filter(|hook| !hook.is_invalid())
+            .collect();
+        if hooks.len() != ranges.len() {
+            for hook in hooks {
+                let _ = UnhookWinEvent(hook);
+            }
+            let _ = ready_tx.send(Err("SetWinEventHook failed".to_string()));
+            return;
+        }
+        let _ = ready_tx.send(Ok(()));
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        for hook in hooks {
+            let _ = UnhookWinEvent(hook);
+        }
+    });
+    ready_rx
+        .recv()
+        .map_err(|_| "event hook thread exited")?
+        .map_err(Into::into)
+}
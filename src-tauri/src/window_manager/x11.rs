@@ -0,0 +1,503 @@
+use super::{WindowEvent, WindowInfo, WindowStyle, WindowTracker};
+use crate::geometry::PhysicalRect;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, MapState, Window,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+x11rb::atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_WM_NAME,
+        _NET_FRAME_EXTENTS,
+        _NET_WM_PID,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_UTILITY,
+        _NET_WM_WINDOW_TYPE_TOOLBAR,
+        _NET_WM_WINDOW_TYPE_POPUP_MENU,
+        _NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
+        _NET_WM_WINDOW_TYPE_TOOLTIP,
+        _NET_WM_WINDOW_TYPE_NOTIFICATION,
+        _NET_WM_WINDOW_TYPE_COMBO,
+        UTF8_STRING,
+    }
+}
+
+/// EWMH 対応ウィンドウマネージャ上の X11 実装
+///
+/// `WindowInfo::hwnd` には X11 の Window ID をそのまま入れる。
+pub struct X11WindowTracker {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+}
+
+impl X11WindowTracker {
+    /// `DISPLAY` 環境変数の X サーバーに接続する
+    pub fn connect() -> Result<Self, Box<dyn std::error::Error>> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        Ok(Self { conn, root, atoms })
+    }
+
+    fn active_window(&self) -> Option<Window> {
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )
+            .ok()?
+            .reply()
+            .ok()?;
+        let window = reply.value32()?.next();
+        window.filter(|&w| w != x11rb::NONE)
+    }
+
+    /// `_NET_CLIENT_LIST_STACKING` は背面から前面の順なので反転して返す
+    fn stacking_order(&self) -> Vec<Window> {
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_CLIENT_LIST_STACKING,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+        let mut windows: Vec<Window> = reply
+            .and_then(|r| r.value32().map(|v| v.collect()))
+            .unwrap_or_default();
+        windows.reverse();
+        windows
+    }
+
+    fn title(&self, window: Window) -> String {
+        // _NET_WM_NAME (UTF-8) を優先し、無ければ WM_NAME にフォールバック
+        let net_name = self
+            .string_property(window, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)
+            .filter(|s| !s.is_empty());
+        net_name
+            .or_else(|| {
+                self.string_property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into())
+            })
+            .unwrap_or_default()
+    }
+
+    /// WM_CLASS は "instance\0class\0" 形式なので class 側を返す
+    fn class_name(&self, window: Window) -> String {
+        let raw = self
+            .string_property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())
+            .unwrap_or_default();
+        let mut parts = raw.split('\0');
+        let instance = parts.next().unwrap_or_default();
+        parts
+            .next()
+            .filter(|c| !c.is_empty())
+            .unwrap_or(instance)
+            .to_string()
+    }
+
+    fn pid(&self, window: Window) -> u32 {
+        self.property32(window, self.atoms._NET_WM_PID, AtomEnum::CARDINAL.into())
+            .first()
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// WM_TRANSIENT_FOR で指定された親（所有者）ウィンドウ
+    fn owner(&self, window: Window) -> Option<isize> {
+        self.property32(
+            window,
+            AtomEnum::WM_TRANSIENT_FOR.into(),
+            AtomEnum::WINDOW.into(),
+        )
+        .first()
+        .copied()
+        .filter(|&w| w != x11rb::NONE)
+        .map(|w| w as isize)
+    }
+
+    /// _NET_WM_WINDOW_TYPE / _NET_WM_STATE を OS 非依存のスタイルに変換する
+    fn styles(&self, window: Window) -> Vec<WindowStyle> {
+        let atoms = &self.atoms;
+        let mut styles = Vec::new();
+        for window_type in self.property32(window, atoms._NET_WM_WINDOW_TYPE, AtomEnum::ATOM.into())
+        {
+            let style = if window_type == atoms._NET_WM_WINDOW_TYPE_UTILITY
+                || window_type == atoms._NET_WM_WINDOW_TYPE_TOOLBAR
+            {
+                WindowStyle::ToolWindow
+            } else if window_type == atoms._NET_WM_WINDOW_TYPE_POPUP_MENU
+                || window_type == atoms._NET_WM_WINDOW_TYPE_DROPDOWN_MENU
+                || window_type == atoms._NET_WM_WINDOW_TYPE_TOOLTIP
+                || window_type == atoms._NET_WM_WINDOW_TYPE_NOTIFICATION
+                || window_type == atoms._NET_WM_WINDOW_TYPE_COMBO
+            {
+                WindowStyle::Popup
+            } else {
+                continue;
+            };
+            if !styles.contains(&style) {
+                styles.push(style);
+            }
+        }
+        if self
+            .property32(window, atoms._NET_WM_STATE, AtomEnum::ATOM.into())
+            .contains(&atoms._NET_WM_STATE_ABOVE)
+        {
+            styles.push(WindowStyle::Topmost);
+        }
+        styles
+    }
+
+    fn property32(&self, window: Window, property: u32, type_: u32) -> Vec<u32> {
+        self.conn
+            .get_property(false, window, property, type_, 0, 1024)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|r| r.value32().map(|v| v.collect()))
+            .unwrap_or_default()
+    }
+
+    fn string_property(&self, window: Window, property: u32, type_: u32) -> Option<String> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, type_, 0, 1024)
+            .ok()?
+            .reply()
+            .ok()?;
+        let bytes: Vec<u8> = reply.value8()?.collect();
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    fn is_viewable(&self, window: Window) -> bool {
+        self.conn
+            .get_window_attributes(window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|attrs| attrs.map_state == MapState::VIEWABLE)
+    }
+
+    /// クライアント領域をルート座標に変換し、WM の装飾 (_NET_FRAME_EXTENTS) 分広げる
+    fn frame_rect(&self, window: Window) -> PhysicalRect {
+        let geometry = self
+            .conn
+            .get_geometry(window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+
+        let (Some(geometry), Some(origin)) = (geometry, origin) else {
+            return PhysicalRect::default();
+        };
+
+        // _NET_FRAME_EXTENTS: left, right, top, bottom
+        let extents = self.property32(
+            window,
+            self.atoms._NET_FRAME_EXTENTS,
+            AtomEnum::CARDINAL.into(),
+        );
+        let extent = |i: usize| extents.get(i).copied().unwrap_or(0) as i32;
+
+        let client = PhysicalRect::from_origin_size(
+            origin.dst_x as i32,
+            origin.dst_y as i32,
+            u32::from(geometry.width),
+            u32::from(geometry.height),
+        );
+        PhysicalRect {
+            left: client.left - extent(0),
+            top: client.top - extent(2),
+            right: client.right + extent(1),
+            bottom: client.bottom + extent(3),
+        }
+    }
+
+    fn read_window(&self, window: Window) -> WindowInfo {
+        let pid = self.pid(window);
+        WindowInfo {
+            hwnd: window as isize,
+            title: self.title(window),
+            class_name: self.class_name(window),
+            process_name: String::new(),
+            pid,
+            owner: self.owner(window),
+            styles: self.styles(window),
+            visible: self.is_viewable(window),
+            rect: self.frame_rect(window),
+            // 角丸の有無は WM やテーマ次第で、取得する標準的な方法がない
+            corner_radius: 0,
+        }
+    }
+}
+
+impl WindowTracker for X11WindowTracker {
+    fn foreground_window(&self) -> Option<WindowInfo> {
+        self.active_window().map(|w| self.read_window(w))
+    }
+
+    fn window_below(&self, hwnd: isize) -> Option<WindowInfo> {
+        let order = self.stacking_order();
+        let i = order.iter().position(|&w| w as isize == hwnd)?;
+        order.get(i + 1).map(|&w| self.read_window(w))
+    }
+
+    fn z_ordered_windows(&self) -> Vec<WindowInfo> {
+        self.stacking_order()
+            .into_iter()
+            .map(|w| self.read_window(w))
+            .collect()
+    }
+
+    /// /proc/<pid>/exe を辿って実行ファイル名を得る
+    fn process_name(&self, pid: u32) -> String {
+        if pid == 0 {
+            return String::new();
+        }
+        std::fs::read_link(format!("/proc/{}/exe", pid))
+            .ok()
+            .and_then(|exe| exe.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .unwrap_or_default()
+    }
+}
+
+/// ルートウィンドウを監視し、アクティブウィンドウ・重なり順・移動/リサイズの変化を通知するスレッドを起動する
+///
+/// 問い合わせ用の接続とは別に接続する（イベント待ちで問い合わせを塞がないため）。
+pub fn start_event_watcher() -> Result<(), Box<dyn std::error::Error>> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let atoms = Atoms::new(&conn)?.reply()?;
+    conn.change_window_attributes(
+        root,
+        &ChangeWindowAttributesAux::new()
+            .event_mask(EventMask::PROPERTY_CHANGE | EventMask::SUBSTRUCTURE_NOTIFY),
+    )?
+    .check()?;
+
+    std::thread::spawn(move || {
+        while let Ok(event) = conn.wait_for_event() {
+            let event = match event {
+                Event::PropertyNotify(e) if e.atom == atoms._NET_ACTIVE_WINDOW => {
+                    let active = conn
+                        .get_property(false, root, e.atom, AtomEnum::WINDOW, 0, 1)
+                        .ok()
+                        .and_then(|cookie| cookie.reply().ok())
+                        .and_then(|r| r.value32().and_then(|mut v| v.next()))
+                        .unwrap_or(x11rb::NONE);
+                    WindowEvent::Foreground(active as isize)
+                }
+                Event::PropertyNotify(e) if e.atom == atoms._NET_CLIENT_LIST_STACKING => {
+                    WindowEvent::ZOrder(x11rb::NONE as isize)
+                }
+                Event::ConfigureNotify(e) => WindowEvent::MoveResize(e.window as isize),
+                Event::MapNotify(e) => WindowEvent::ZOrder(e.window as isize),
+                Event::UnmapNotify(e) => WindowEvent::ZOrder(e.window as isize),
+                Event::DestroyNotify(e) => WindowEvent::ZOrder(e.window as isize),
+                _ => continue,
+            };
+            super::publish_window_event(event);
+        }
+    });
+    Ok(())
+}
+
+/// ウィンドウマネージャのいない X サーバー（Xvfb）上で、WM が付けるはずのプロパティを
+/// 自分で書いて EWMH の読み取りを確かめる。X サーバーが要るので既定では走らない:
+///
+/// `xvfb-run cargo test x11 -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+
+    struct Server {
+        conn: RustConnection,
+        root: Window,
+        atoms: Atoms,
+    }
+
+    impl Server {
+        fn connect() -> Self {
+            let (conn, screen_num) =
+                x11rb::connect(None).expect("tests need an X server (run under xvfb-run)");
+            let root = conn.setup().roots[screen_num].root;
+            let atoms = Atoms::new(&conn).unwrap().reply().unwrap();
+            Self { conn, root, atoms }
+        }
+
+        fn create_window(&self, parent: Window, x: i16, y: i16, width: u16, height: u16) -> Window {
+            let window = self.conn.generate_id().unwrap();
+            self.conn
+                .create_window(
+                    x11rb::COPY_DEPTH_FROM_PARENT,
+                    window,
+                    parent,
+                    x,
+                    y,
+                    width,
+                    height,
+                    0,
+                    WindowClass::INPUT_OUTPUT,
+                    x11rb::COPY_FROM_PARENT,
+                    &CreateWindowAux::new(),
+                )
+                .unwrap();
+            self.conn.map_window(window).unwrap();
+            window
+        }
+
+        fn set32(&self, window: Window, property: u32, type_: impl Into<u32>, values: &[u32]) {
+            self.conn
+                .change_property32(PropMode::REPLACE, window, property, type_, values)
+                .unwrap();
+        }
+
+        fn set8(&self, window: Window, property: impl Into<u32>, type_: u32, value: &[u8]) {
+            self.conn
+                .change_property8(PropMode::REPLACE, window, property, type_, value)
+                .unwrap();
+        }
+
+        fn sync(&self) {
+            self.conn.sync().unwrap();
+        }
+    }
+
+    impl Drop for Server {
+        fn drop(&mut self) {
+            let _ = self
+                .conn
+                .delete_property(self.root, self.atoms._NET_ACTIVE_WINDOW);
+            let _ = self
+                .conn
+                .delete_property(self.root, self.atoms._NET_CLIENT_LIST_STACKING);
+            let _ = self.conn.flush();
+        }
+    }
+
+    #[test]
+    #[ignore = "needs an X server (xvfb-run)"]
+    fn reads_ewmh_properties_in_root_coordinates() {
+        let server = Server::connect();
+        let atoms = &server.atoms;
+        // WM が付ける枠の代わりに親ウィンドウを作り、その中にクライアントを置く
+        let frame = server.create_window(server.root, 100, 50, 330, 260);
+        let client = server.create_window(frame, 5, 20, 320, 240);
+        let back = server.create_window(server.root, 0, 0, 50, 50);
+        let tracker_pid = std::process::id();
+
+        server.set32(
+            client,
+            atoms._NET_FRAME_EXTENTS,
+            AtomEnum::CARDINAL,
+            &[5, 5, 20, 5],
+        );
+        server.set32(
+            client,
+            atoms._NET_WM_PID,
+            AtomEnum::CARDINAL,
+            &[tracker_pid],
+        );
+        server.set32(
+            client,
+            AtomEnum::WM_TRANSIENT_FOR.into(),
+            AtomEnum::WINDOW,
+            &[back],
+        );
+        server.set32(
+            client,
+            atoms._NET_WM_WINDOW_TYPE,
+            AtomEnum::ATOM,
+            &[atoms._NET_WM_WINDOW_TYPE_UTILITY],
+        );
+        server.set32(
+            client,
+            atoms._NET_WM_STATE,
+            AtomEnum::ATOM,
+            &[atoms._NET_WM_STATE_ABOVE],
+        );
+        server.set8(
+            client,
+            atoms._NET_WM_NAME,
+            atoms.UTF8_STRING,
+            "エディタ".as_bytes(),
+        );
+        server.set8(back, AtomEnum::WM_NAME, AtomEnum::STRING.into(), b"Back");
+        server.set8(
+            client,
+            AtomEnum::WM_CLASS,
+            AtomEnum::STRING.into(),
+            b"editor\0Editor\0",
+        );
+        // _NET_CLIENT_LIST_STACKING は背面から前面の順
+        server.set32(
+            server.root,
+            atoms._NET_CLIENT_LIST_STACKING,
+            AtomEnum::WINDOW,
+            &[back, client],
+        );
+        server.set32(
+            server.root,
+            atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW,
+            &[client],
+        );
+        server.sync();
+
+        let tracker = X11WindowTracker::connect().unwrap();
+        let active = tracker.foreground_window().expect("active window");
+        assert_eq!(active.hwnd, client as isize);
+        assert_eq!(active.title, "エディタ");
+        assert_eq!(active.class_name, "Editor");
+        assert_eq!(active.pid, tracker_pid);
+        assert!(!tracker.process_name(active.pid).is_empty());
+        assert_eq!(active.owner, Some(back as isize));
+        assert_eq!(
+            active.styles,
+            [WindowStyle::ToolWindow, WindowStyle::Topmost]
+        );
+        assert!(active.visible);
+        // クライアントはルート座標で (105, 70)、そこから枠の分だけ広げる
+        assert_eq!(
+            active.rect,
+            PhysicalRect {
+                left: 100,
+                top: 50,
+                right: 105 + 320 + 5,
+                bottom: 70 + 240 + 5,
+            }
+        );
+
+        let order: Vec<isize> = tracker.z_ordered_windows().iter().map(|w| w.hwnd).collect();
+        assert_eq!(order, [client as isize, back as isize]);
+        let below = tracker.window_below(client as isize).expect("window below");
+        assert_eq!(below.hwnd, back as isize);
+        assert_eq!(below.title, "Back");
+        assert_eq!(below.rect, PhysicalRect::from_origin_size(0, 0, 50, 50));
+        assert!(tracker.window_below(back as isize).is_none());
+
+        server.conn.unmap_window(client).unwrap();
+        server.sync();
+        assert!(!tracker.foreground_window().unwrap().visible);
+    }
+}
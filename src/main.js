@@ -1,8 +1,9 @@
 const { invoke } = window.__TAURI__.core;
 const { getCurrentWebviewWindow } = window.__TAURI__.webviewWindow;
+const { listen } = window.__TAURI__.event;
 
 window.onerror = function (msg, url, line, col, error) {
-  invoke('js_log', { message: `JS ERROR: ${msg} at ${line}:${col}` });
+  invoke('js_log', { message: `JS ERROR: ${msg} at ${line}:${col}`, level: 'error' });
 };
 
 const appWindow = getCurrentWebviewWindow();
@@ -12,6 +13,12 @@ const hole = document.getElementById('hole');
 const blurRange = document.getElementById('blur-range');
 const blurValue = document.getElementById('blur-value');
 const closeBtn = document.getElementById('close-btn');
+const settingsError = document.getElementById('settings-error');
+const backendSelect = document.getElementById('backend-select');
+const backendApplied = document.getElementById('backend-applied');
+const themeSelect = document.getElementById('theme-select');
+const tintColor = document.getElementById('tint-color');
+const tintAlpha = document.getElementById('tint-alpha');
 
 const maskTop = document.getElementById('mask-top');
 const maskBottom = document.getElementById('mask-bottom');
@@ -22,14 +29,14 @@ let scaleFactor = 1.0;
 
 let isOverlayMode = false;
 
-async function updateHole() {
+// info: Rust 側から `active-window-changed` で届く WindowInfo（無ければ null）
+async function updateHole(info) {
   if (appWindow.label !== 'overlay') return;
 
   try {
     // スケール係数はモニタ移動で変わるため、常に最新を取得
     scaleFactor = await appWindow.scaleFactor();
 
-    const info = await invoke('get_active_window');
     if (info && info.rect) {
       const rect = info.rect;
 
@@ -72,18 +79,18 @@ async function updateHole() {
       hole.style.width = width + 'px';
       hole.style.height = height + 'px';
 
-      invoke('js_log', { message: `Mask update: ${left},${top} ${width}x${height}` });
+      invoke('js_log', { message: `Mask update: ${left},${top} ${width}x${height}`, level: 'trace' });
     } else {
       [maskTop, maskBottom, maskLeft, maskRight, hole].forEach(el => el.style.display = 'none');
     }
   } catch (e) {
-    invoke('js_log', { message: `Error in updateHole: ${e}` });
+    invoke('js_log', { message: `Error in updateHole: ${e}`, level: 'error' });
   }
 }
 
 // 初期設定
 async function init() {
-  invoke('js_log', { message: `Init called` });
+  invoke('js_log', { message: `Init called`, level: 'debug' });
   try {
     scaleFactor = await appWindow.scaleFactor();
     invoke('js_log', { message: `Init - window label: ${appWindow.label}, scale: ${scaleFactor}` });
@@ -91,19 +98,112 @@ async function init() {
     if (appWindow.label === 'overlay') {
       settingsUI.classList.add('hidden');
       overlayContainer.classList.remove('hidden');
-      setInterval(updateHole, 100);
+      // 初回だけ取りに行き、以降はアクティブウィンドウが変わったときに Rust 側から通知される
+      updateHole(await invoke('get_active_window'));
+      await listen('active-window-changed', (event) => updateHole(event.payload));
     } else {
       settingsUI.classList.remove('hidden');
       overlayContainer.classList.add('hidden');
+
+      // 設定ファイルが不正なら Rust 側は直前の設定のまま動き、理由だけ通知してくる
+      await listen('settings-error', (event) => {
+        settingsError.textContent = `設定ファイルを読み込めませんでした: ${event.payload}`;
+        settingsError.classList.remove('hidden');
+      });
+      await listen('settings-changed', (event) => {
+        settingsError.classList.add('hidden');
+        showBlurStrength(event.payload.blur_strength);
+        showBackdrop(event.payload);
+      });
+      await listen('overlay-backend-changed', async () => {
+        showBackdropStatus(await invoke('get_backdrop_status'));
+      });
+
+      const settings = await invoke('get_settings');
+      showBlurStrength(settings.blur_strength);
+      showBackdrop(settings);
+      showBackdropStatus(await invoke('get_backdrop_status'));
     }
   } catch (e) {
-    invoke('js_log', { message: `Init failed: ${e}` });
+    invoke('js_log', { message: `Init failed: ${e}`, level: 'error' });
+  }
+}
+
+function showBlurStrength(strength) {
+  blurRange.value = strength;
+  blurValue.textContent = strength + 'px';
+}
+
+function showBackdrop(settings) {
+  themeSelect.value = settings.backdrop_theme;
+  const [r, g, b, a] = settings.tint;
+  tintColor.value = '#' + [r, g, b].map((c) => c.toString(16).padStart(2, '0')).join('');
+  tintAlpha.value = a;
+}
+
+// status: BackdropStatus（使えなかった方式は選べなくする）
+function showBackdropStatus(status) {
+  for (const option of backendSelect.options) {
+    option.disabled = status.unavailable.includes(option.value);
   }
+  backendSelect.value = status.requested;
+  const applied = backendSelect.querySelector(`option[value="${status.applied}"]`);
+  backendApplied.textContent = status.applied === status.requested
+    ? ''
+    : `（使えないため ${applied ? applied.textContent : 'なし'} で表示中）`;
+}
+
+function showCommandError(action, err) {
+  // err は CommandError（{ kind, errors | message }）
+  const detail = err.kind === 'invalid'
+    ? err.errors.map((v) => `${v.field}: ${v.message}`).join(', ')
+    : err.message;
+  settingsError.textContent = `${action}: ${detail}`;
+  settingsError.classList.remove('hidden');
 }
 
+backendSelect.addEventListener('change', async (e) => {
+  try {
+    await invoke('set_overlay_backend', { backend: e.target.value });
+  } catch (err) {
+    showCommandError('背景を切り替えられませんでした', err);
+  }
+  showBackdropStatus(await invoke('get_backdrop_status'));
+});
+
+themeSelect.addEventListener('change', async (e) => {
+  try {
+    await invoke('set_backdrop_theme', { theme: e.target.value });
+  } catch (err) {
+    showCommandError('明暗を変更できませんでした', err);
+  }
+});
+
+async function saveTint() {
+  const hex = tintColor.value;
+  const tint = [1, 3, 5].map((i) => parseInt(hex.slice(i, i + 2), 16));
+  tint.push(Number(tintAlpha.value));
+  try {
+    await invoke('set_backdrop_tint', { tint });
+  } catch (err) {
+    showCommandError('色を変更できませんでした', err);
+  }
+}
+
+tintColor.addEventListener('change', saveTint);
+tintAlpha.addEventListener('change', saveTint);
+
 blurRange.addEventListener('input', (e) => {
   blurValue.textContent = e.target.value + 'px';
-  // 将来的にシェーダパラメータを更新する
+});
+
+// ドラッグ中は表示だけ更新し、離したときに保存する
+blurRange.addEventListener('change', async (e) => {
+  try {
+    await invoke('set_blur_strength', { strength: Number(e.target.value) });
+  } catch (err) {
+    showCommandError('ブラー強度を変更できませんでした', err);
+  }
 });
 
 closeBtn.addEventListener('click', () => {